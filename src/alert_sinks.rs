@@ -0,0 +1,432 @@
+//! Where alerts go besides the browser, configured with `[[alerts.sinks]]`.
+
+use crate::{
+  error::{bail, err_msg, Result},
+  events::{format_mac, Alert, Severity},
+};
+use log::{error, warn};
+use serde::Deserialize;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::{
+  fs::File,
+  io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+  net::{TcpStream, UdpSocket},
+  sync::mpsc,
+  task::JoinHandle,
+  time,
+};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+// for the sinks to send what's queued when shutting down
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// alerts waiting for each sink, more are dropped
+const SINK_QUEUE_CAPACITY: usize = 100;
+
+// user-level messages
+const SYSLOG_FACILITY: u8 = 1;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum AlertSinkConfig {
+  /// one JSON alert per line
+  Stdout,
+  /// one JSON alert per line, appended
+  File { path: PathBuf },
+  /// `/dev/log`, or UDP to `address`
+  Syslog { address: Option<String> },
+  /// POSTs each alert as JSON, plain http only
+  Webhook { url: String },
+}
+
+impl AlertSinkConfig {
+  /// For logs, a webhook url can have a token in it.
+  fn kind(&self) -> &'static str {
+    match self {
+      Self::Stdout => "stdout",
+      Self::File { .. } => "file",
+      Self::Syslog { .. } => "syslog",
+      Self::Webhook { .. } => "webhook",
+    }
+  }
+
+  pub fn validate(&self) -> Result<()> {
+    if let Self::Webhook { url } = self {
+      WebhookUrl::parse(url)?;
+    }
+
+    Ok(())
+  }
+}
+
+struct WebhookUrl {
+  // host:port for the Host header
+  authority: String,
+  host: String,
+  port: u16,
+  path: String,
+}
+
+impl WebhookUrl {
+  fn parse(url: &str) -> Result<Self> {
+    let rest = url
+      .strip_prefix("http://")
+      .ok_or_else(|| err_msg(format!("webhook url must start with http://: {}", url)))?;
+
+    let (authority, path) = match rest.find('/') {
+      Some(i) => rest.split_at(i),
+      None => (rest, "/"),
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+      Some((host, port)) => (host, port.parse()?),
+      None => (authority, 80),
+    };
+    if host.is_empty() {
+      bail!("no host in webhook url {}", url);
+    }
+
+    Ok(Self {
+      authority: authority.to_string(),
+      host: host.to_string(),
+      port,
+      path: path.to_string(),
+    })
+  }
+}
+
+async fn send_request(url: &WebhookUrl, body: &str) -> Result<()> {
+  let mut stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+
+  let head = format!(
+      "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+      url.path,
+      url.authority,
+      body.len()
+    );
+  stream.write_all(head.as_bytes()).await?;
+  stream.write_all(body.as_bytes()).await?;
+  stream.flush().await?;
+
+  let mut status_line = String::new();
+  BufReader::new(stream).read_line(&mut status_line).await?;
+
+  // HTTP/1.1 200 OK
+  let status: u16 = status_line
+    .split_whitespace()
+    .nth(1)
+    .and_then(|status| status.parse().ok())
+    .ok_or_else(|| err_msg(format!("bad webhook response {:?}", status_line)))?;
+  if !(200..300).contains(&status) {
+    bail!("webhook responded {}", status);
+  }
+
+  Ok(())
+}
+
+/// Sends `body` as a JSON POST, fails on anything but a 2xx response.
+async fn post_json(url: &WebhookUrl, body: &str) -> Result<()> {
+  time::timeout(WEBHOOK_TIMEOUT, send_request(url, body))
+    .await
+    .map_err(|_| err_msg("webhook timed out"))?
+}
+
+fn syslog_message(alert: &Alert) -> String {
+  let severity = match alert.severity {
+    Severity::Critical => 2,
+    Severity::Warning => 4,
+    Severity::Info => 6,
+  };
+
+  let vendors: Vec<String> = alert
+    .macs
+    .iter()
+    .zip(&alert.vendors)
+    .filter_map(|(mac, vendor)| Some(format!("{} {}", format_mac(*mac), (*vendor)?)))
+    .collect();
+
+  let mut message = format!(
+    "<{}>wifi-visualizer[{}]: {}",
+    SYSLOG_FACILITY * 8 + severity,
+    std::process::id(),
+    alert.message
+  );
+  if !vendors.is_empty() {
+    message.push_str(&format!(" ({})", vendors.join(", ")));
+  }
+
+  message
+}
+
+enum Sink {
+  Stdout,
+  File(File),
+  #[cfg(unix)]
+  LocalSyslog(tokio::net::UnixDatagram),
+  Syslog(UdpSocket),
+  Webhook(WebhookUrl),
+}
+
+impl Sink {
+  fn open(config: &AlertSinkConfig) -> Result<Self> {
+    Ok(match config {
+      AlertSinkConfig::Stdout => Self::Stdout,
+
+      AlertSinkConfig::File { path } => Self::File(File::from_std(
+        std::fs::OpenOptions::new()
+          .create(true)
+          .append(true)
+          .open(path)?,
+      )),
+
+      #[cfg(unix)]
+      AlertSinkConfig::Syslog { address: None } => {
+        let socket = tokio::net::UnixDatagram::unbound()?;
+        socket.connect("/dev/log")?;
+        Self::LocalSyslog(socket)
+      }
+
+      #[cfg(not(unix))]
+      AlertSinkConfig::Syslog { address: None } => {
+        bail!("syslog needs an address on this platform")
+      }
+
+      AlertSinkConfig::Syslog {
+        address: Some(address),
+      } => {
+        let socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(address)?;
+        socket.set_nonblocking(true)?;
+        Self::Syslog(UdpSocket::from_std(socket)?)
+      }
+
+      AlertSinkConfig::Webhook { url } => Self::Webhook(WebhookUrl::parse(url)?),
+    })
+  }
+
+  async fn send(&mut self, alert: &Alert, json: &str) -> Result<()> {
+    match self {
+      Self::Stdout => println!("{}", json),
+
+      Self::File(file) => {
+        file.write_all(format!("{}\n", json).as_bytes()).await?;
+        file.flush().await?;
+      }
+
+      #[cfg(unix)]
+      Self::LocalSyslog(socket) => {
+        socket.send(syslog_message(alert).as_bytes()).await?;
+      }
+
+      Self::Syslog(socket) => {
+        socket.send(syslog_message(alert).as_bytes()).await?;
+      }
+
+      Self::Webhook(url) => post_json(url, json).await?,
+    }
+
+    Ok(())
+  }
+}
+
+struct SinkQueue {
+  kind: &'static str,
+  sender: mpsc::Sender<Arc<(Alert, String)>>,
+  task: JoinHandle<()>,
+  dropped: u64,
+}
+
+/// Every sink runs on its own task, so a slow one only falls behind itself.
+pub struct AlertSinks {
+  queues: Vec<SinkQueue>,
+}
+
+impl AlertSinks {
+  /// Opens every sink, `None` when there are none.
+  pub fn spawn(configs: &[AlertSinkConfig]) -> Result<Option<Self>> {
+    if configs.is_empty() {
+      return Ok(None);
+    }
+
+    let mut queues = Vec::new();
+    for config in configs {
+      let kind = config.kind();
+      let mut sink =
+        Sink::open(config).map_err(|e| err_msg(format!("opening {} alert sink: {}", kind, e)))?;
+
+      let (sender, mut receiver) = mpsc::channel::<Arc<(Alert, String)>>(SINK_QUEUE_CAPACITY);
+      let task = tokio::spawn(async move {
+        while let Some(alert) = receiver.recv().await {
+          let (alert, json) = &*alert;
+          if let Err(e) = sink.send(alert, json).await {
+            warn!("sending alert: {}", e);
+          }
+        }
+      });
+
+      queues.push(SinkQueue {
+        kind,
+        sender,
+        task,
+        dropped: 0,
+      });
+    }
+
+    Ok(Some(Self { queues }))
+  }
+
+  /// Drops the alert for sinks that are too far behind.
+  pub fn send(&mut self, alert: &Alert) {
+    let json = match serde_json::to_string(alert) {
+      Ok(json) => json,
+      Err(e) => {
+        error!("serializing alert: {}", e);
+        return;
+      }
+    };
+    let alert = Arc::new((alert.clone(), json));
+
+    for queue in &mut self.queues {
+      if let Err(mpsc::error::TrySendError::Full(_)) = queue.sender.try_send(alert.clone()) {
+        queue.dropped += 1;
+        // 1, 2, 4, 8, ... so a stuck sink doesn't flood the log too
+        if queue.dropped.is_power_of_two() {
+          warn!(
+            "{} alert sink is falling behind, dropped {} alerts",
+            queue.kind, queue.dropped
+          );
+        }
+      }
+    }
+  }
+
+  /// Waits a little for the sinks to send what's still queued.
+  pub async fn close(self) {
+    let deadline = time::Instant::now() + CLOSE_TIMEOUT;
+
+    for SinkQueue {
+      kind, sender, task, ..
+    } in self.queues
+    {
+      // the task finishes once the queue is empty
+      drop(sender);
+
+      if time::timeout_at(deadline, task).await.is_err() {
+        warn!(
+          "{} alert sink didn't finish sending, dropping the rest",
+          kind
+        );
+      }
+    }
+  }
+}
+
+#[test]
+fn test_syslog_message() {
+  use crate::events::{AlertKind, RogueApReason};
+  use ieee80211::MacAddress;
+
+  let bssid = MacAddress::from_bytes(&[0x10, 0x00, 0x90, 1, 2, 3]).unwrap();
+  let alert = Alert::new(
+    Severity::Warning,
+    AlertKind::RogueAccessPoint {
+      bssid,
+      ssid: "corp".to_string(),
+      reason: RogueApReason::VendorMismatch,
+    },
+    "rogue".to_string(),
+  );
+
+  assert_eq!(
+    syslog_message(&alert),
+    format!(
+      "<12>wifi-visualizer[{}]: rogue (10-00-90-01-02-03 HP)",
+      std::process::id()
+    )
+  );
+}
+
+#[test]
+fn test_close_sends_queued_alerts() {
+  use crate::events::{AlertKind, RogueApReason};
+  use ieee80211::MacAddress;
+
+  let path = std::env::temp_dir().join(format!("alert-sinks-{}.jsonl", std::process::id()));
+  let bssid = MacAddress::from_bytes(&[0x10, 0x00, 0x90, 1, 2, 3]).unwrap();
+  let alert = Alert::new(
+    Severity::Warning,
+    AlertKind::RogueAccessPoint {
+      bssid,
+      ssid: "corp".to_string(),
+      reason: RogueApReason::VendorMismatch,
+    },
+    "rogue".to_string(),
+  );
+
+  tokio::runtime::Runtime::new().unwrap().block_on(async {
+    let mut sinks = AlertSinks::spawn(&[AlertSinkConfig::File { path: path.clone() }])
+      .unwrap()
+      .unwrap();
+    for _ in 0..3 {
+      sinks.send(&alert);
+    }
+    sinks.close().await;
+  });
+
+  let written = std::fs::read_to_string(&path).unwrap();
+  std::fs::remove_file(&path).unwrap();
+  assert_eq!(written.lines().count(), 3);
+}
+
+#[test]
+fn test_webhook() {
+  use tokio::{io::AsyncReadExt, net::TcpListener};
+
+  tokio::runtime::Runtime::new().unwrap().block_on(async {
+    // a stub server that answers one request and hands back the body
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = tokio::spawn(async move {
+      let (stream, _) = listener.accept().await.unwrap();
+      let mut reader = BufReader::new(stream);
+
+      let mut request_line = String::new();
+      reader.read_line(&mut request_line).await.unwrap();
+
+      let mut content_length = 0;
+      loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+          break;
+        }
+
+        if let Some(length) = line.strip_prefix("Content-Length: ") {
+          content_length = length.parse().unwrap();
+        }
+      }
+
+      let mut body = vec![0; content_length];
+      reader.read_exact(&mut body).await.unwrap();
+
+      reader
+        .into_inner()
+        .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+        .await
+        .unwrap();
+
+      (request_line, String::from_utf8(body).unwrap())
+    });
+
+    let url = WebhookUrl::parse(&format!("http://127.0.0.1:{}/alerts", port)).unwrap();
+    post_json(&url, r#"{"kind":"test"}"#).await.unwrap();
+
+    let (request_line, body) = server.await.unwrap();
+    assert_eq!(request_line, "POST /alerts HTTP/1.1\r\n");
+    assert_eq!(body, r#"{"kind":"test"}"#);
+
+    assert!(WebhookUrl::parse("https://example.com/").is_err());
+  });
+}
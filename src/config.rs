@@ -0,0 +1,54 @@
+use crate::{
+  error::Result,
+  events::{
+    AlertsConfig, BeaconFloodConfig, DeauthFloodConfig, DistanceConfig, KarmaConfig,
+    PositioningConfig, RogueApConfig, WatchlistConfig,
+  },
+};
+use serde::Deserialize;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+/// Optional settings from `--config <FILE>`, a TOML file.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+  /// estimate distances from signal strength
+  pub distance: Option<DistanceConfig>,
+  /// estimate positions from what several sensors hear
+  pub positioning: Option<PositioningConfig>,
+  /// where alerts go and how many
+  #[serde(default)]
+  pub alerts: AlertsConfig,
+  #[serde(default)]
+  pub deauth_flood: DeauthFloodConfig,
+  /// networks we expect, for spotting evil twins
+  #[serde(default)]
+  pub rogue_ap: RogueApConfig,
+  #[serde(default)]
+  pub karma: KarmaConfig,
+  #[serde(default)]
+  pub beacon_flood: BeaconFloodConfig,
+  /// newer IEEE `oui.csv`, `mam.csv` or `oui36.csv` files, over the built in
+  /// vendors
+  #[serde(default)]
+  pub oui_csv: Vec<PathBuf>,
+  /// labelled addresses and SSIDs to keep an eye on
+  #[serde(default)]
+  pub watchlist: WatchlistConfig,
+}
+
+impl Config {
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let text = fs::read_to_string(path)?;
+    let config: Self = toml::from_str(&text)?;
+
+    config.alerts.validate()?;
+    config.rogue_ap.validate()?;
+    config.watchlist.validate()?;
+
+    Ok(config)
+  }
+}
@@ -0,0 +1,113 @@
+use super::{Band, PhyInfo, PhyKind};
+use serde::Serialize;
+use std::time::Duration;
+
+// 802.11b PLCP preamble and header
+const DSSS_LONG_PREAMBLE_US: f64 = 192.0;
+const DSSS_SHORT_PREAMBLE_US: f64 = 96.0;
+// legacy OFDM training fields and SIGNAL, sent first by HT/VHT too
+const OFDM_PREAMBLE_US: f64 = 20.0;
+// HT-SIG or VHT-SIG-A
+const HT_SIG_US: f64 = 8.0;
+// each STF/LTF/VHT-SIG-B
+const TRAINING_FIELD_US: f64 = 4.0;
+const SYMBOL_US: f64 = 4.0;
+const SHORT_GI_SYMBOL_US: f64 = 3.6;
+// SERVICE and tail bits around the frame
+const OFDM_EXTRA_BITS: f64 = 22.0;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChannelUtilization {
+  /// estimated % of the last interval the channel was busy with frames we heard
+  pub busy: f64,
+  pub airtime_us: u64,
+  pub frames: u64,
+}
+
+/// How long a `length` byte frame (including FCS) was on the air, `None` if
+/// the rate isn't known. Doesn't include the gaps and ACKs around it.
+pub fn frame_airtime(
+  phy_info: &PhyInfo,
+  length: usize,
+  short_preamble: bool,
+  short_gi: bool,
+) -> Option<Duration> {
+  let rate = f64::from(phy_info.rate.filter(|rate| *rate > 0.0)?);
+  let bits = f64::from(u32::try_from(length).ok()?) * 8.0;
+
+  let ofdm = |preamble_us: f64, symbol_us: f64| {
+    let bits_per_symbol = rate * symbol_us;
+    preamble_us + ((bits + OFDM_EXTRA_BITS) / bits_per_symbol).ceil() * symbol_us
+  };
+
+  let spatial_streams = f64::from(phy_info.spatial_streams.unwrap_or(1));
+  let symbol_us = if short_gi {
+    SHORT_GI_SYMBOL_US
+  } else {
+    SYMBOL_US
+  };
+
+  let airtime_us = match phy_info.kind? {
+    PhyKind::Legacy => {
+      let is_dsss = phy_info.band == Some(Band::Ghz2) && [1.0, 2.0, 5.5, 11.0].contains(&rate);
+
+      if is_dsss {
+        let preamble_us = if short_preamble {
+          DSSS_SHORT_PREAMBLE_US
+        } else {
+          DSSS_LONG_PREAMBLE_US
+        };
+
+        preamble_us + bits / rate
+      } else {
+        ofdm(OFDM_PREAMBLE_US, SYMBOL_US)
+      }
+    }
+
+    // HT-STF, one HT-LTF per stream
+    PhyKind::Ht => ofdm(
+      OFDM_PREAMBLE_US + HT_SIG_US + TRAINING_FIELD_US * (1.0 + spatial_streams),
+      symbol_us,
+    ),
+
+    // VHT-STF, one VHT-LTF per stream, VHT-SIG-B
+    PhyKind::Vht => ofdm(
+      OFDM_PREAMBLE_US + HT_SIG_US + TRAINING_FIELD_US * (2.0 + spatial_streams),
+      symbol_us,
+    ),
+  };
+
+  #[allow(clippy::cast_possible_truncation)]
+  #[allow(clippy::cast_sign_loss)]
+  let airtime_ns = (airtime_us * 1000.0).round() as u64;
+
+  Some(Duration::from_nanos(airtime_ns))
+}
+
+#[test]
+fn test_frame_airtime() {
+  let legacy = |rate, band| PhyInfo {
+    kind: Some(PhyKind::Legacy),
+    rate: Some(rate),
+    mcs: None,
+    spatial_streams: Some(1),
+    band: Some(band),
+    frequency: None,
+    antenna: None,
+    snr: None,
+  };
+
+  // 192us preamble + 800 bits at 1Mbps
+  let airtime = frame_airtime(&legacy(1.0, Band::Ghz2), 100, false, false).unwrap();
+  assert_eq!(airtime.as_micros(), 992);
+
+  // 20us preamble + ceil((12000 + 22) / 216) 4us symbols
+  let airtime = frame_airtime(&legacy(54.0, Band::Ghz5), 1500, false, false).unwrap();
+  assert_eq!(airtime.as_micros(), 244);
+
+  let unknown_rate = PhyInfo {
+    rate: None,
+    ..legacy(1.0, Band::Ghz2)
+  };
+  assert!(frame_airtime(&unknown_rate, 100, false, false).is_none());
+}
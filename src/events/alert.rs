@@ -0,0 +1,289 @@
+use super::{vendor, RogueApReason};
+use crate::{
+  alert_sinks::AlertSinkConfig,
+  error::{bail, Result},
+};
+use ieee80211::MacAddress;
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{HashMap, VecDeque},
+  time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Info,
+  Warning,
+  Critical,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", content = "details")]
+pub enum AlertKind {
+  DeauthFlood {
+    attacker: MacAddress,
+    victims: Vec<MacAddress>,
+    /// sent to the broadcast address, kicking everyone
+    broadcast: bool,
+    /// frames/s over the window
+    rate: f64,
+  },
+
+  RogueAccessPoint {
+    bssid: MacAddress,
+    ssid: String,
+    reason: RogueApReason,
+  },
+
+  BeaconFlood {
+    /// new within the window
+    access_points: usize,
+    /// new access points/s
+    rate: f64,
+    random_ssids: usize,
+    /// one sequence counter behind many BSSIDs
+    shared_sequence_numbers: bool,
+    same_signal: bool,
+  },
+
+  /// answers probe requests for any SSID
+  Karma {
+    bssid: MacAddress,
+    ssids: Vec<String>,
+  },
+}
+
+impl AlertKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::DeauthFlood { .. } => "DeauthFlood",
+      Self::RogueAccessPoint { .. } => "RogueAccessPoint",
+      Self::BeaconFlood { .. } => "BeaconFlood",
+      Self::Karma { .. } => "Karma",
+    }
+  }
+
+  /// Addresses involved, attacker first.
+  pub fn macs(&self) -> Vec<MacAddress> {
+    match self {
+      Self::DeauthFlood {
+        attacker, victims, ..
+      } => std::iter::once(*attacker)
+        .chain(victims.iter().copied())
+        .collect(),
+      Self::RogueAccessPoint { bssid, .. } | Self::Karma { bssid, .. } => vec![*bssid],
+      Self::BeaconFlood { .. } => Vec::new(),
+    }
+  }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Alert {
+  pub severity: Severity,
+  #[serde(flatten)]
+  pub kind: AlertKind,
+  pub macs: Vec<MacAddress>,
+  /// of each of `macs`
+  pub vendors: Vec<Option<&'static str>>,
+  pub message: String,
+  /// milliseconds since the unix epoch
+  pub timestamp: u64,
+}
+
+impl Alert {
+  pub fn new(severity: Severity, kind: AlertKind, message: String) -> Self {
+    let timestamp = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |duration| {
+        u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+      });
+
+    let macs = kind.macs();
+    let vendors = macs.iter().map(|mac| vendor(mac.as_bytes())).collect();
+
+    Self {
+      severity,
+      macs,
+      vendors,
+      kind,
+      message,
+      timestamp,
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+  /// less severe alerts are dropped
+  pub min_severity: Severity,
+  /// the same kind of alert about the same addresses is only sent once within this
+  pub dedup_secs: u64,
+  /// across all kinds
+  pub max_per_minute: usize,
+  /// show alerts in the browser
+  pub websocket: bool,
+  pub sinks: Vec<AlertSinkConfig>,
+}
+
+impl Default for AlertsConfig {
+  fn default() -> Self {
+    Self {
+      min_severity: Severity::Info,
+      dedup_secs: 60,
+      max_per_minute: 30,
+      websocket: true,
+      sinks: Vec::new(),
+    }
+  }
+}
+
+impl AlertsConfig {
+  pub fn validate(&self) -> Result<()> {
+    if self.max_per_minute == 0 {
+      bail!("alerts.max_per_minute must be at least 1");
+    }
+
+    for sink in &self.sinks {
+      sink.validate()?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Duplicates and alerts over the rate limit dropped since the last report.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SuppressedAlerts {
+  pub duplicates: u64,
+  pub rate_limited: u64,
+}
+
+/// Drops alerts below the minimum severity, duplicates, and too many at once.
+/// Critical alerts aren't rate limited.
+pub struct AlertLimiter {
+  min_severity: Severity,
+  dedup: Duration,
+  max_per_minute: usize,
+
+  // (kind, macs) -> last sent
+  last_sent: HashMap<(&'static str, Vec<MacAddress>), Instant>,
+  // within the last minute, not critical
+  sent: VecDeque<Instant>,
+
+  suppressed: SuppressedAlerts,
+  last_report: Instant,
+}
+
+impl AlertLimiter {
+  pub fn new(config: &AlertsConfig) -> Self {
+    Self {
+      min_severity: config.min_severity,
+      dedup: Duration::from_secs(config.dedup_secs),
+      max_per_minute: config.max_per_minute,
+      last_sent: HashMap::new(),
+      sent: VecDeque::new(),
+      suppressed: SuppressedAlerts::default(),
+      last_report: Instant::now(),
+    }
+  }
+
+  pub fn allow(&mut self, now: Instant, alert: &Alert) -> bool {
+    if alert.severity < self.min_severity {
+      return false;
+    }
+
+    let dedup = self.dedup;
+    self
+      .last_sent
+      .retain(|_key, time| now.duration_since(*time) < dedup);
+    while let Some(time) = self.sent.front() {
+      if now.duration_since(*time) < RATE_LIMIT_WINDOW {
+        break;
+      }
+      self.sent.pop_front();
+    }
+
+    let key = (alert.kind.name(), alert.macs.clone());
+    if self.last_sent.contains_key(&key) {
+      self.suppressed.duplicates += 1;
+      return false;
+    }
+
+    let is_critical = alert.severity == Severity::Critical;
+    if !is_critical && self.sent.len() >= self.max_per_minute {
+      self.suppressed.rate_limited += 1;
+      return false;
+    }
+
+    self.last_sent.insert(key, now);
+    if !is_critical {
+      self.sent.push_back(now);
+    }
+    true
+  }
+
+  /// At most once a minute, when anything was dropped since the last time.
+  pub fn take_suppressed(&mut self, now: Instant) -> Option<SuppressedAlerts> {
+    if now.duration_since(self.last_report) < RATE_LIMIT_WINDOW
+      || self.suppressed == SuppressedAlerts::default()
+    {
+      return None;
+    }
+    self.last_report = now;
+
+    Some(std::mem::take(&mut self.suppressed))
+  }
+}
+
+#[test]
+fn test_alert_limiter() {
+  use super::test_mac;
+
+  let alert = |severity, last| {
+    Alert::new(
+      severity,
+      AlertKind::Karma {
+        bssid: test_mac([0x02, 0, 0, 0, 0, last]),
+        ssids: Vec::new(),
+      },
+      String::new(),
+    )
+  };
+
+  let mut limiter = AlertLimiter::new(&AlertsConfig {
+    min_severity: Severity::Warning,
+    max_per_minute: 3,
+    ..AlertsConfig::default()
+  });
+  let start = Instant::now();
+
+  assert!(!limiter.allow(start, &alert(Severity::Info, 1)));
+
+  assert!(limiter.allow(start, &alert(Severity::Warning, 1)));
+  // duplicate
+  assert!(!limiter.allow(start, &alert(Severity::Critical, 1)));
+
+  assert!(limiter.allow(start, &alert(Severity::Warning, 2)));
+  assert!(limiter.allow(start, &alert(Severity::Warning, 3)));
+  // over the rate limit
+  assert!(!limiter.allow(start, &alert(Severity::Warning, 4)));
+  // except for critical ones
+  assert!(limiter.allow(start, &alert(Severity::Critical, 5)));
+
+  let later = start + Duration::from_secs(61);
+  assert_eq!(
+    limiter.take_suppressed(later),
+    Some(SuppressedAlerts {
+      duplicates: 1,
+      rate_limited: 1,
+    })
+  );
+  assert_eq!(limiter.take_suppressed(later), None);
+
+  assert!(limiter.allow(later, &alert(Severity::Warning, 1)));
+  assert!(limiter.allow(later, &alert(Severity::Warning, 4)));
+}
@@ -0,0 +1,493 @@
+use super::{Alert, AlertKind, Severity};
+use ieee80211::MacAddress;
+use serde::Deserialize;
+use std::{
+  collections::{HashMap, VecDeque},
+  time::Duration,
+};
+
+/// New access points allowed within `window_secs` before alerting.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BeaconFloodConfig {
+  pub window_secs: u64,
+  pub new_access_points: usize,
+  /// hide the fake access points behind one node
+  pub collapse: bool,
+}
+
+impl Default for BeaconFloodConfig {
+  fn default() -> Self {
+    // even walking into a crowded area doesn't find this many at once
+    Self {
+      window_secs: 5,
+      new_access_points: 50,
+      collapse: false,
+    }
+  }
+}
+
+// forget fake access points not heard from in this long
+const FAKE_TIMEOUT: Duration = Duration::from_secs(300);
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(10);
+// a flood's shared counter moves on by about one per beacon
+const SHARED_SEQUENCE_GAP: u16 = 2;
+const SHARED_SEQUENCE_RUN: u16 = 128;
+// signals of one radio
+const SAME_SIGNAL_SPREAD: i16 = 3;
+// beacons in a row with its own sequence numbers before a fake access point
+// is believed to be real, probe responses come in between
+const NORMAL_BEACONS: u32 = 10;
+const MAX_OWN_SEQUENCE_STEP: u16 = 16;
+
+struct NewAccessPoint {
+  time: Duration,
+  bssid: MacAddress,
+  random_ssid: bool,
+  sequence_number: Option<u16>,
+  signal: Option<i8>,
+}
+
+/// What the access points of a flood have in common, any of it.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct FloodPattern {
+  random_ssids: bool,
+  // last value of the shared counter
+  sequence_number: Option<u16>,
+  // min, max
+  signal: Option<(i8, i8)>,
+}
+
+impl FloodPattern {
+  fn is_empty(&self) -> bool {
+    !self.random_ssids && self.sequence_number.is_none() && self.signal.is_none()
+  }
+
+  fn matches(&self, ap: &NewAccessPoint) -> bool {
+    let sequence_continues = match (self.sequence_number, ap.sequence_number) {
+      (Some(last), Some(next)) => sequence_step(last, next) <= SHARED_SEQUENCE_RUN,
+      _ => false,
+    };
+    let same_signal = match (self.signal, ap.signal) {
+      (Some((min, max)), Some(signal)) => {
+        i16::from(min) - SAME_SIGNAL_SPREAD <= i16::from(signal)
+          && i16::from(signal) <= i16::from(max) + SAME_SIGNAL_SPREAD
+      }
+      _ => false,
+    };
+
+    (self.random_ssids && ap.random_ssid) || sequence_continues || same_signal
+  }
+}
+
+struct FakeAccessPoint {
+  last_seen: Duration,
+  last_sequence_number: Option<u16>,
+  // in a row, see NORMAL_BEACONS
+  normal_beacons: u32,
+}
+
+/// Tools like mdk4 beaconing hundreds of made up BSSIDs. Times are capture
+/// timestamps, so files played back quickly look the same as live.
+pub struct BeaconFloodDetector {
+  config: BeaconFloodConfig,
+  window: Duration,
+
+  recent: VecDeque<NewAccessPoint>,
+  // new access points like these are fake until then, moved along while the
+  // flood lasts
+  flooding_until: Option<Duration>,
+  pattern: FloodPattern,
+  fake: HashMap<MacAddress, FakeAccessPoint>,
+  next_expire: Duration,
+  // not taken yet, only kept when collapsing
+  new_fake: Vec<MacAddress>,
+}
+
+/// 12 bit sequence numbers, forward from `last`.
+fn sequence_step(last: u16, next: u16) -> u16 {
+  next.wrapping_sub(last) & 0x0fff
+}
+
+fn sequence_gap(a: u16, b: u16) -> u16 {
+  sequence_step(a, b).min(sequence_step(b, a))
+}
+
+/// Random strings switch between lowercase, uppercase, digits and symbols a lot.
+fn looks_random(ssid: &[u8]) -> bool {
+  let Ok(ssid) = std::str::from_utf8(ssid) else {
+    return true;
+  };
+  if ssid.chars().any(char::is_control) {
+    return true;
+  }
+
+  let class = |c: char| {
+    if c.is_lowercase() {
+      0
+    } else if c.is_uppercase() {
+      1
+    } else if c.is_numeric() {
+      2
+    } else {
+      3
+    }
+  };
+
+  let chars: Vec<char> = ssid.chars().collect();
+  if chars.len() < 6 {
+    return false;
+  }
+
+  let switches = chars
+    .windows(2)
+    .filter(|pair| class(pair[0]) != class(pair[1]))
+    .count();
+  switches * 2 > chars.len()
+}
+
+impl BeaconFloodDetector {
+  pub fn new(config: BeaconFloodConfig) -> Self {
+    Self {
+      window: Duration::from_secs(config.window_secs),
+      config,
+      recent: VecDeque::new(),
+      flooding_until: None,
+      pattern: FloodPattern::default(),
+      fake: HashMap::new(),
+      next_expire: Duration::ZERO,
+      new_fake: Vec::new(),
+    }
+  }
+
+  pub fn is_fake(&self, bssid: MacAddress) -> bool {
+    self.fake.contains_key(&bssid)
+  }
+
+  /// Fake and should be left out of the graph.
+  pub fn is_hidden(&self, bssid: MacAddress) -> bool {
+    self.config.collapse && self.is_fake(bssid)
+  }
+
+  pub fn hidden(&self) -> Vec<MacAddress> {
+    if self.config.collapse {
+      self.fake.keys().copied().collect()
+    } else {
+      Vec::new()
+    }
+  }
+
+  /// Hidden since the last call.
+  pub fn take_hidden(&mut self) -> Vec<MacAddress> {
+    self.new_fake.drain(..).collect()
+  }
+
+  fn is_flooding(&self, now: Duration) -> bool {
+    self.flooding_until.map_or(false, |until| now < until)
+  }
+
+  pub fn new_access_point(
+    &mut self,
+    now: Duration,
+    bssid: MacAddress,
+    ssid: &[u8],
+    sequence_number: Option<u16>,
+    signal: Option<i8>,
+  ) -> Option<Alert> {
+    while let Some(front) = self.recent.front() {
+      if now.saturating_sub(front.time) < self.window {
+        break;
+      }
+      self.recent.pop_front();
+    }
+
+    let ap = NewAccessPoint {
+      time: now,
+      bssid,
+      random_ssid: looks_random(ssid),
+      sequence_number,
+      signal,
+    };
+
+    if self.is_flooding(now) {
+      if self.pattern.matches(&ap) {
+        self.flooding_until = Some(now + self.window);
+        if sequence_number.is_some() && self.pattern.sequence_number.is_some() {
+          self.pattern.sequence_number = sequence_number;
+        }
+        self.mark_fake(now, bssid, sequence_number);
+      }
+      self.recent.push_back(ap);
+      return None;
+    }
+
+    self.recent.push_back(ap);
+    if self.recent.len() <= self.config.new_access_points {
+      return None;
+    }
+
+    // lots of new access points alone could be a crowded place, or a file
+    // played back quickly
+    let alert = self.alert()?;
+    self.flooding_until = Some(now + self.window);
+
+    let fake: Vec<(MacAddress, Option<u16>)> = self
+      .recent
+      .iter()
+      .filter(|ap| self.pattern.matches(ap) || self.shares_sequence_numbers(ap))
+      .map(|ap| (ap.bssid, ap.sequence_number))
+      .collect();
+    for (bssid, sequence_number) in fake {
+      self.mark_fake(now, bssid, sequence_number);
+    }
+
+    Some(alert)
+  }
+
+  /// Another beacon from a fake access point, true once it's beaconed like a
+  /// real one for long enough to not be fake after all.
+  pub fn fake_beacon(
+    &mut self,
+    now: Duration,
+    bssid: MacAddress,
+    sequence_number: Option<u16>,
+  ) -> bool {
+    let flooding = self.is_flooding(now);
+    let Some(fake) = self.fake.get_mut(&bssid) else {
+      return false;
+    };
+
+    // a flood's beacons jump ahead by every other fake access point's
+    let normal = match (fake.last_sequence_number, sequence_number) {
+      (Some(last), Some(next)) => (1..=MAX_OWN_SEQUENCE_STEP).contains(&sequence_step(last, next)),
+      _ => !flooding,
+    };
+
+    fake.last_seen = now;
+    if sequence_number.is_some() {
+      fake.last_sequence_number = sequence_number;
+    }
+    fake.normal_beacons = if normal { fake.normal_beacons + 1 } else { 0 };
+
+    if flooding {
+      if let (Some(last), Some(next)) = (self.pattern.sequence_number, sequence_number) {
+        if sequence_step(last, next) <= SHARED_SEQUENCE_RUN {
+          self.pattern.sequence_number = Some(next);
+        }
+      }
+    }
+
+    if fake.normal_beacons < NORMAL_BEACONS {
+      return false;
+    }
+
+    self.fake.remove(&bssid);
+    self.new_fake.retain(|new_fake| *new_fake != bssid);
+    true
+  }
+
+  /// Forgets fake access points that went quiet.
+  pub fn expire(&mut self, now: Duration) {
+    if now < self.next_expire {
+      return;
+    }
+    self.next_expire = now + EXPIRE_INTERVAL;
+
+    self
+      .fake
+      .retain(|_bssid, fake| now.saturating_sub(fake.last_seen) < FAKE_TIMEOUT);
+  }
+
+  fn shares_sequence_numbers(&self, ap: &NewAccessPoint) -> bool {
+    let (Some(_), Some(sequence_number)) = (self.pattern.sequence_number, ap.sequence_number)
+    else {
+      return false;
+    };
+
+    self.recent.iter().any(|other| {
+      other.bssid != ap.bssid
+        && other.sequence_number.map_or(false, |other| {
+          sequence_gap(other, sequence_number) <= SHARED_SEQUENCE_GAP
+        })
+    })
+  }
+
+  fn mark_fake(&mut self, now: Duration, bssid: MacAddress, sequence_number: Option<u16>) {
+    if self.fake.contains_key(&bssid) {
+      return;
+    }
+
+    self.fake.insert(
+      bssid,
+      FakeAccessPoint {
+        last_seen: now,
+        last_sequence_number: sequence_number,
+        normal_beacons: 0,
+      },
+    );
+    if self.config.collapse {
+      self.new_fake.push(bssid);
+    }
+  }
+
+  /// Sets `pattern`, `None` when the new access points have nothing in common.
+  fn alert(&mut self) -> Option<Alert> {
+    let access_points = self.recent.len();
+
+    #[allow(clippy::cast_precision_loss)]
+    let rate = access_points as f64 / self.window.as_secs_f64();
+
+    let random_ssids = self.recent.iter().filter(|ap| ap.random_ssid).count();
+
+    // one counter shared by all the "different" BSSIDs
+    let mut sequence_numbers: Vec<u16> = self
+      .recent
+      .iter()
+      .filter_map(|ap| ap.sequence_number)
+      .collect();
+    sequence_numbers.sort_unstable();
+    let close = sequence_numbers
+      .windows(2)
+      .filter(|pair| pair[1] - pair[0] <= SHARED_SEQUENCE_GAP)
+      .count();
+    let shared_sequence_numbers = close * 2 > sequence_numbers.len();
+
+    // most of them from one radio
+    let mut signals: Vec<i8> = self.recent.iter().filter_map(|ap| ap.signal).collect();
+    signals.sort_unstable();
+    let mut signal_band = None;
+    let mut start = 0;
+    for (end, signal) in signals.iter().enumerate() {
+      while i16::from(*signal) - i16::from(signals[start]) > SAME_SIGNAL_SPREAD {
+        start += 1;
+      }
+      if signal_band.map_or(true, |(start_band, end_band)| {
+        end - start > end_band - start_band
+      }) {
+        signal_band = Some((start, end));
+      }
+    }
+    let same_signal = signal_band
+      .filter(|(start, end)| (end - start + 1) * 2 > signals.len())
+      .map(|(start, end)| (signals[start], signals[end]));
+
+    let pattern = FloodPattern {
+      random_ssids: random_ssids * 2 > access_points,
+      sequence_number: if shared_sequence_numbers {
+        self.recent.iter().rev().find_map(|ap| ap.sequence_number)
+      } else {
+        None
+      },
+      signal: same_signal,
+    };
+    if pattern.is_empty() {
+      return None;
+    }
+
+    let mut message = format!(
+      "beacon flood, {} new access points at {:.1}/s",
+      access_points, rate
+    );
+    if pattern.random_ssids {
+      message += ", random SSIDs";
+    }
+    if shared_sequence_numbers {
+      message += ", shared sequence numbers";
+    }
+    if same_signal.is_some() {
+      message += ", same signal";
+    }
+
+    let alert = Alert::new(
+      Severity::Warning,
+      AlertKind::BeaconFlood {
+        access_points,
+        rate,
+        random_ssids,
+        shared_sequence_numbers,
+        same_signal: same_signal.is_some(),
+      },
+      message,
+    );
+    self.pattern = pattern;
+
+    Some(alert)
+  }
+}
+
+#[test]
+fn test_beacon_flood() {
+  let mut detector = BeaconFloodDetector::new(BeaconFloodConfig {
+    collapse: true,
+    ..BeaconFloodConfig::default()
+  });
+  let start = Duration::from_secs(1000);
+
+  let real = MacAddress::from_bytes(&[0x00, 0x11, 0x22, 0, 0, 1]).unwrap();
+  assert!(detector
+    .new_access_point(start, real, b"HomeNetwork-5G", Some(1234), Some(-70))
+    .is_none());
+
+  let mut alerts = Vec::new();
+  for i in 0..100_u8 {
+    let bssid = MacAddress::from_bytes(&[0x02, 0, 0, 0, 0, i]).unwrap();
+    let ssid = format!("x{}K#q{}Z", i, i);
+    let now = start + Duration::from_secs(10) + Duration::from_millis(u64::from(i) * 10);
+
+    alerts.extend(detector.new_access_point(
+      now,
+      bssid,
+      ssid.as_bytes(),
+      Some(u16::from(i)),
+      Some(-30),
+    ));
+  }
+
+  assert_eq!(alerts.len(), 1);
+  assert!(matches!(
+    alerts[0].kind,
+    AlertKind::BeaconFlood {
+      access_points: 51,
+      random_ssids: 51,
+      shared_sequence_numbers: true,
+      same_signal: true,
+      ..
+    }
+  ));
+
+  assert!(!detector.is_fake(real));
+  assert_eq!(detector.take_hidden().len(), 100);
+  assert!(detector.take_hidden().is_empty());
+
+  // a real access point caught up in it keeps beaconing with its own counter
+  let caught = MacAddress::from_bytes(&[0x02, 0, 0, 0, 0, 5]).unwrap();
+  let later = start + Duration::from_secs(30);
+  let unmarked: Vec<bool> = (1000..=1010)
+    .map(|sequence_number| detector.fake_beacon(later, caught, Some(sequence_number)))
+    .collect();
+  assert_eq!(unmarked.iter().filter(|unmarked| **unmarked).count(), 1);
+  assert_eq!(unmarked.last(), Some(&true));
+  assert!(!detector.is_fake(caught));
+
+  // lots of ordinary access points at once, like starting up in a crowd
+  let mut detector = BeaconFloodDetector::new(BeaconFloodConfig::default());
+  for i in 0..60_u8 {
+    let bssid = MacAddress::from_bytes(&[0x00, 0x11, 0x22, 0, 1, i]).unwrap();
+    let ssid = format!("Network {}", i);
+    let signal = -40 - i8::try_from(i % 40).unwrap();
+
+    assert!(detector
+      .new_access_point(
+        start,
+        bssid,
+        ssid.as_bytes(),
+        Some(u16::from(i) * 67),
+        Some(signal),
+      )
+      .is_none());
+    assert!(!detector.is_fake(bssid));
+  }
+
+  assert!(!looks_random(b"HomeNetwork-5G"));
+  assert!(!looks_random(b"eduroam"));
+}
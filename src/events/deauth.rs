@@ -0,0 +1,350 @@
+use super::{format_mac, is_broadcast, Alert, AlertKind, Severity};
+use ieee80211::MacAddress;
+use serde::Deserialize;
+use std::{
+  collections::{HashMap, HashSet, VecDeque},
+  time::Duration,
+};
+
+/// Deauth/disassoc frames allowed within `window_secs` before alerting.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeauthFloodConfig {
+  pub window_secs: u64,
+  /// from one transmitter
+  pub per_source: usize,
+  /// to one receiver
+  pub per_target: usize,
+  /// from one transmitter to the broadcast address
+  pub broadcast: usize,
+}
+
+impl Default for DeauthFloodConfig {
+  fn default() -> Self {
+    // a normal disconnect is a handful of frames
+    Self {
+      window_secs: 5,
+      per_source: 30,
+      per_target: 30,
+      broadcast: 10,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Trigger {
+  Source(MacAddress),
+  Target(MacAddress),
+  Broadcast(MacAddress),
+}
+
+/// Counts deauth/disassoc frames in a sliding window of capture time, so files
+/// played back quickly look the same as live.
+pub struct DeauthFloodDetector {
+  config: DeauthFloodConfig,
+  window: Duration,
+
+  // capture time, source, target
+  frames: VecDeque<(Duration, MacAddress, MacAddress)>,
+  per_source: HashMap<MacAddress, usize>,
+  per_target: HashMap<MacAddress, usize>,
+  broadcast_per_source: HashMap<MacAddress, usize>,
+
+  // only alert once per window for the same thing
+  last_alerts: HashMap<Trigger, Duration>,
+}
+
+fn increment(counts: &mut HashMap<MacAddress, usize>, mac: MacAddress) -> usize {
+  let count = counts.entry(mac).or_insert(0);
+  *count += 1;
+  *count
+}
+
+fn decrement(counts: &mut HashMap<MacAddress, usize>, mac: MacAddress) {
+  if let Some(count) = counts.get_mut(&mac) {
+    *count -= 1;
+    if *count == 0 {
+      counts.remove(&mac);
+    }
+  }
+}
+
+impl DeauthFloodDetector {
+  pub fn new(config: DeauthFloodConfig) -> Self {
+    Self {
+      window: Duration::from_secs(config.window_secs),
+      config,
+      frames: VecDeque::new(),
+      per_source: HashMap::new(),
+      per_target: HashMap::new(),
+      broadcast_per_source: HashMap::new(),
+      last_alerts: HashMap::new(),
+    }
+  }
+
+  /// `now` is the frame's capture timestamp.
+  pub fn frame(&mut self, now: Duration, source: MacAddress, target: MacAddress) -> Vec<Alert> {
+    self.expire(now);
+
+    self.frames.push_back((now, source, target));
+
+    let mut triggers = Vec::new();
+
+    if increment(&mut self.per_source, source) > self.config.per_source {
+      triggers.push(Trigger::Source(source));
+    }
+
+    if is_broadcast(target) {
+      if increment(&mut self.broadcast_per_source, source) > self.config.broadcast {
+        triggers.push(Trigger::Broadcast(source));
+      }
+    } else if increment(&mut self.per_target, target) > self.config.per_target {
+      triggers.push(Trigger::Target(target));
+    }
+
+    triggers
+      .into_iter()
+      .filter_map(|trigger| self.alert(now, trigger))
+      .collect()
+  }
+
+  fn expire(&mut self, now: Duration) {
+    while let Some((time, source, target)) = self.frames.front().copied() {
+      if now.saturating_sub(time) < self.window {
+        break;
+      }
+      self.frames.pop_front();
+
+      decrement(&mut self.per_source, source);
+      if is_broadcast(target) {
+        decrement(&mut self.broadcast_per_source, source);
+      } else {
+        decrement(&mut self.per_target, target);
+      }
+    }
+
+    let window = self.window;
+    self
+      .last_alerts
+      .retain(|_trigger, time| now.saturating_sub(*time) < window);
+  }
+
+  fn alert(&mut self, now: Duration, trigger: Trigger) -> Option<Alert> {
+    if self.last_alerts.contains_key(&trigger) {
+      return None;
+    }
+    self.last_alerts.insert(trigger, now);
+
+    let matching: Vec<(MacAddress, MacAddress)> = self
+      .frames
+      .iter()
+      .filter(|(_time, source, target)| match trigger {
+        Trigger::Source(mac) => *source == mac,
+        Trigger::Target(mac) => *target == mac,
+        Trigger::Broadcast(mac) => *source == mac && is_broadcast(*target),
+      })
+      .map(|(_time, source, target)| (*source, *target))
+      .collect();
+
+    #[allow(clippy::cast_precision_loss)]
+    let rate = matching.len() as f64 / self.window.as_secs_f64();
+
+    let (attacker, victims, broadcast) = match trigger {
+      Trigger::Source(attacker) => {
+        let mut seen = HashSet::new();
+        let victims: Vec<MacAddress> = matching
+          .iter()
+          .map(|(_source, target)| *target)
+          .filter(|target| !is_broadcast(*target) && seen.insert(*target))
+          .collect();
+        let broadcast = matching
+          .iter()
+          .any(|(_source, target)| is_broadcast(*target));
+
+        (attacker, victims, broadcast)
+      }
+
+      Trigger::Target(victim) => {
+        // usually one spoofed source, blame the busiest
+        let mut counts = HashMap::new();
+        for (source, _target) in &matching {
+          increment(&mut counts, *source);
+        }
+        let (attacker, _count) = counts.into_iter().max_by_key(|(_source, count)| *count)?;
+
+        (attacker, vec![victim], false)
+      }
+
+      Trigger::Broadcast(attacker) => (attacker, Vec::new(), true),
+    };
+
+    let message = format!(
+      "deauth flood from {} against {} at {:.1} frames/s",
+      format_mac(attacker),
+      if broadcast && victims.is_empty() {
+        "everyone".to_string()
+      } else {
+        victims
+          .iter()
+          .map(|victim| format_mac(*victim))
+          .collect::<Vec<_>>()
+          .join(", ")
+      },
+      rate
+    );
+
+    Some(Alert::new(
+      Severity::Critical,
+      AlertKind::DeauthFlood {
+        attacker,
+        victims,
+        broadcast,
+        rate,
+      },
+      message,
+    ))
+  }
+}
+
+#[test]
+fn test_deauth_flood() {
+  use super::{handle_frame, Event, Store};
+  use crate::{config::Config, packet_capture::FrameWithRadiotap};
+  use ieee80211::Frame;
+
+  let attacker = [0x02, 0, 0, 0, 0, 0xaa];
+  let victim = [0x02, 0, 0, 0, 0, 0x01];
+
+  // frame control, duration, receiver, transmitter, bssid, sequence, reason
+  let deauth = |receiver: [u8; 6], sequence: u16| {
+    let mut bytes = vec![0xc0, 0x00, 0x3a, 0x01];
+    bytes.extend_from_slice(&receiver);
+    bytes.extend_from_slice(&attacker);
+    bytes.extend_from_slice(&attacker);
+    bytes.extend_from_slice(&(sequence << 4).to_le_bytes());
+    bytes.extend_from_slice(&7_u16.to_le_bytes());
+    bytes
+  };
+
+  let mut store = Store::new(&Config::default());
+  let mut alerts = Vec::new();
+
+  let config = DeauthFloodConfig::default();
+  for sequence in 0..=u16::try_from(config.per_target).unwrap() {
+    let frame_with_radiotap = FrameWithRadiotap {
+      id: u64::from(sequence),
+      frame: Frame::new(deauth(victim, sequence)),
+      radiotap: None,
+      bad_fcs: false,
+      signal: None,
+      antenna_signals: Vec::new(),
+      length: 30,
+      sensor: None,
+      timestamp: Duration::from_millis(u64::from(sequence)),
+    };
+
+    for event in handle_frame(&mut store, &frame_with_radiotap).unwrap() {
+      if let Event::Alert(alert) = event {
+        alerts.push(alert);
+      }
+    }
+  }
+
+  // per_source and per_target both go over on the same frame, the second is a
+  // duplicate
+  assert_eq!(alerts.len(), 1);
+  for alert in alerts {
+    assert!(matches!(
+      &alert.kind,
+      AlertKind::DeauthFlood {
+        attacker: alert_attacker,
+        victims,
+        broadcast: false,
+        ..
+      } if alert_attacker.as_bytes() == &attacker[..]
+        && victims.len() == 1
+        && victims[0].as_bytes() == &victim[..]
+    ));
+  }
+}
+
+#[test]
+fn test_deauth_broadcast_flood() {
+  let attacker = MacAddress::from_bytes(&[0x02, 0, 0, 0, 0, 0xaa]).unwrap();
+  let broadcast = MacAddress::from_bytes(&[0xff; 6]).unwrap();
+
+  let mut detector = DeauthFloodDetector::new(DeauthFloodConfig::default());
+
+  // slow enough that the window never fills
+  for i in 0..100 {
+    let now = Duration::from_secs(i);
+    assert!(detector.frame(now, attacker, broadcast).is_empty());
+  }
+
+  let now = Duration::from_secs(1000);
+  let alerts: Vec<Alert> = (0..=10)
+    .flat_map(|_| detector.frame(now, attacker, broadcast))
+    .collect();
+  assert_eq!(alerts.len(), 1);
+  assert!(matches!(
+    alerts[0].kind,
+    AlertKind::DeauthFlood {
+      broadcast: true,
+      ..
+    }
+  ));
+}
+
+#[test]
+fn test_deauth_flood_capture() {
+  use super::{handle_frame, Event, Store};
+  use crate::{
+    config::Config,
+    packet_capture::{decode_packet, get_packet_stream, CaptureType, Packet},
+  };
+  use futures::prelude::*;
+
+  // 40 deauths a second apart from 02:00:00:00:00:aa, then 40 within 0.4s from
+  // 02:00:00:00:00:bb, read as fast as possible
+  let (is_radiotap, packets) = get_packet_stream(CaptureType::File(
+    "caps/deauth-flood.pcap".to_string(),
+    false,
+  ))
+  .unwrap();
+  let packets: Vec<Packet> = tokio::runtime::Runtime::new()
+    .unwrap()
+    .block_on(packets.try_collect())
+    .unwrap();
+
+  let mut store = Store::new(&Config::default());
+  let mut attackers = Vec::new();
+
+  for (id, packet) in packets.into_iter().enumerate() {
+    let Packet::Data(timestamp, bytes) = packet else {
+      continue;
+    };
+    let frame_with_radiotap = decode_packet(
+      u64::try_from(id).unwrap(),
+      is_radiotap,
+      timestamp,
+      bytes,
+      None,
+    )
+    .unwrap();
+
+    for event in handle_frame(&mut store, &frame_with_radiotap).unwrap() {
+      if let Event::Alert(Alert {
+        kind: AlertKind::DeauthFlood { attacker, .. },
+        ..
+      }) = event
+      {
+        attackers.push(attacker);
+      }
+    }
+  }
+
+  assert_eq!(
+    attackers,
+    vec![MacAddress::from_bytes(&[0x02, 0, 0, 0, 0, 0xbb]).unwrap()]
+  );
+}
@@ -0,0 +1,94 @@
+use ieee80211::MacAddress;
+use std::{
+  collections::{HashMap, VecDeque},
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+// how much later another sensor's copy of a frame can arrive
+const DUPLICATE_WINDOW: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FrameKey {
+  transmitter_address: Option<MacAddress>,
+  sequence_number: Option<u16>,
+  fcs: u32,
+}
+
+/// Recognizes the same frame heard by several sensors, so it's only counted
+/// once.
+#[derive(Default)]
+pub struct FrameDeduplicator {
+  // first heard, sensors that heard it
+  recent: HashMap<FrameKey, (Instant, Vec<Arc<str>>)>,
+  // oldest first
+  expiry: VecDeque<(Instant, FrameKey)>,
+}
+
+impl FrameDeduplicator {
+  /// True if another sensor already reported this frame. The same sensor
+  /// reporting it again is a new frame, one ACK looks like any other.
+  pub fn is_duplicate(
+    &mut self,
+    now: Instant,
+    sensor: &Arc<str>,
+    transmitter_address: Option<MacAddress>,
+    sequence_number: Option<u16>,
+    frame: &[u8],
+  ) -> bool {
+    while let Some((time, key)) = self.expiry.front() {
+      if now.duration_since(*time) < DUPLICATE_WINDOW {
+        break;
+      }
+      // unless it was heard again since
+      if self
+        .recent
+        .get(key)
+        .map_or(false, |(first, _)| first == time)
+      {
+        self.recent.remove(key);
+      }
+      self.expiry.pop_front();
+    }
+
+    let key = FrameKey {
+      transmitter_address,
+      sequence_number,
+      fcs: crc32fast::hash(frame),
+    };
+
+    if let Some((_first, sensors)) = self.recent.get_mut(&key) {
+      if !sensors.contains(sensor) {
+        sensors.push(sensor.clone());
+        return true;
+      }
+    }
+
+    self.recent.insert(key, (now, vec![sensor.clone()]));
+    self.expiry.push_back((now, key));
+    false
+  }
+}
+
+#[test]
+fn test_frame_dedup() {
+  let mac = MacAddress::from_bytes(&[0x00, 0x11, 0x22, 0, 0, 1]).unwrap();
+  let (a, b): (Arc<str>, Arc<str>) = ("a".into(), "b".into());
+  let start = Instant::now();
+  let later = |millis| start + Duration::from_millis(millis);
+
+  let mut dedup = FrameDeduplicator::default();
+
+  assert!(!dedup.is_duplicate(start, &a, Some(mac), Some(1), b"frame 1"));
+  assert!(dedup.is_duplicate(later(10), &b, Some(mac), Some(1), b"frame 1"));
+  // a different frame
+  assert!(!dedup.is_duplicate(later(10), &b, Some(mac), Some(2), b"frame 2"));
+
+  // the same bytes from the same sensor again
+  assert!(!dedup.is_duplicate(later(20), &a, None, None, b"ack"));
+  assert!(!dedup.is_duplicate(later(30), &a, None, None, b"ack"));
+  assert!(dedup.is_duplicate(later(40), &b, None, None, b"ack"));
+
+  // too late to be the same one
+  assert!(!dedup.is_duplicate(later(1000), &b, Some(mac), Some(1), b"frame 1"));
+}
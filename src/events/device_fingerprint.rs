@@ -0,0 +1,145 @@
+use super::{ie_fingerprint, parse_oui, IE_VENDOR_SPECIFIC};
+use crate::error::{err_msg, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const BUNDLED_SIGNATURES: &str = include_str!("device_signatures.toml");
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+struct Guess {
+  os: Option<String>,
+  device: Option<String>,
+}
+
+// no deny_unknown_fields, serde doesn't support it with flatten
+#[derive(Deserialize)]
+struct FingerprintSignature {
+  hash: u32,
+  #[serde(flatten)]
+  guess: Guess,
+}
+
+#[derive(Deserialize)]
+struct VendorSignature {
+  oui: String,
+  #[serde(flatten)]
+  guess: Guess,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Signatures {
+  #[serde(default)]
+  fingerprint: Vec<FingerprintSignature>,
+  #[serde(default)]
+  vendor: Vec<VendorSignature>,
+}
+
+/// What a station's probe or association request says about it.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DeviceFingerprint {
+  /// `ie_fingerprint` of the request
+  pub hash: u32,
+  pub os: Option<String>,
+  /// phone, laptop, ...
+  pub device: Option<String>,
+}
+
+impl DeviceFingerprint {
+  pub fn is_known(&self) -> bool {
+    self.os.is_some() || self.device.is_some()
+  }
+}
+
+pub struct SignatureDatabase {
+  fingerprints: HashMap<u32, Guess>,
+  vendors: HashMap<[u8; 3], Guess>,
+}
+
+impl SignatureDatabase {
+  pub fn bundled() -> Self {
+    Self::parse(BUNDLED_SIGNATURES).expect("bundled device signatures")
+  }
+
+  fn parse(text: &str) -> Result<Self> {
+    let signatures: Signatures = toml::from_str(text)?;
+
+    let mut vendors = HashMap::new();
+    for signature in signatures.vendor {
+      let oui =
+        parse_oui(&signature.oui).ok_or_else(|| err_msg(format!("bad oui {:?}", signature.oui)))?;
+      vendors.insert(oui, signature.guess);
+    }
+
+    Ok(Self {
+      fingerprints: signatures
+        .fingerprint
+        .into_iter()
+        .map(|signature| (signature.hash, signature.guess))
+        .collect(),
+      vendors,
+    })
+  }
+
+  /// An exact hash match, or else the first known vendor element.
+  pub fn identify(&self, elements: &[(u8, &[u8])]) -> DeviceFingerprint {
+    let hash = ie_fingerprint(elements.iter().copied());
+
+    let guess = self.fingerprints.get(&hash).or_else(|| {
+      elements
+        .iter()
+        .filter(|(id, data)| *id == IE_VENDOR_SPECIFIC && data.len() >= 3)
+        .find_map(|(_id, data)| self.vendors.get(&[data[0], data[1], data[2]]))
+    });
+    let Guess { os, device } = guess.cloned().unwrap_or_default();
+
+    DeviceFingerprint { hash, os, device }
+  }
+}
+
+#[test]
+fn test_device_fingerprint() {
+  let database = SignatureDatabase::bundled();
+
+  // probe request from the Nokia in caps/Network_Join_Nokia_Mobile.pcap
+  let elements: Vec<(u8, &[u8])> = vec![
+    (0, &b"martinet3"[..]),
+    (1, &[0x82, 0x84, 0x8b, 0x96, 0x0c, 0x12, 0x18, 0x24][..]),
+    (3, &[0x0d][..]),
+    (50, &[0x30, 0x48, 0x60, 0x6c][..]),
+  ];
+  let fingerprint = database.identify(&elements);
+  assert_eq!(fingerprint.hash, 0x132a_cf27);
+  assert_eq!(fingerprint.os.as_deref(), Some("Symbian"));
+  assert_eq!(fingerprint.device.as_deref(), Some("phone"));
+
+  // the SSID and channel don't matter
+  let elements: Vec<(u8, &[u8])> = vec![
+    (0, &b""[..]),
+    (1, &[0x82, 0x84, 0x8b, 0x96, 0x0c, 0x12, 0x18, 0x24][..]),
+    (3, &[0x01][..]),
+    (50, &[0x30, 0x48, 0x60, 0x6c][..]),
+  ];
+  assert_eq!(database.identify(&elements).hash, 0x132a_cf27);
+
+  // most devices are only a hash
+  let elements: Vec<(u8, &[u8])> =
+    vec![(1, &[0x02][..]), (221, &[0x00, 0x17, 0xf2, 0x0a, 0x00][..])];
+  let fingerprint = database.identify(&elements);
+  assert!(!fingerprint.is_known());
+
+  let database = SignatureDatabase::parse(
+    r#"
+    [[vendor]]
+    oui = "00-17-f2"
+    device = "test device"
+    "#,
+  )
+  .unwrap();
+  assert_eq!(
+    database.identify(&elements).device.as_deref(),
+    Some("test device")
+  );
+
+  assert!(SignatureDatabase::parse("[[vendor]]\noui = \"00:17:f2:ff\"").is_err());
+}
@@ -0,0 +1,105 @@
+use super::Band;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathLossModel {
+  /// distance = 10 ^ ((tx power - signal - 20 * log10(MHz) + 27.55) / 20)
+  FreeSpace,
+  /// distance = 10 ^ ((signal at 1m - signal) / (10 * exponent))
+  #[default]
+  LogDistance,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BandPathLoss {
+  /// dBm, transmit power for free-space, signal at 1m for log-distance
+  pub reference_power: f64,
+  /// only used by log-distance, free-space is always 2
+  #[serde(default = "default_path_loss_exponent")]
+  pub path_loss_exponent: f64,
+}
+
+fn default_path_loss_exponent() -> f64 {
+  3.0
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DistanceConfig {
+  pub model: PathLossModel,
+  #[serde(default)]
+  pub bands: HashMap<Band, BandPathLoss>,
+}
+
+impl DistanceConfig {
+  fn band_path_loss(&self, band: Band) -> BandPathLoss {
+    if let Some(band_path_loss) = self.bands.get(&band) {
+      return band_path_loss.clone();
+    }
+
+    // rough indoor values
+    match (self.model, band) {
+      (PathLossModel::FreeSpace, _) => BandPathLoss {
+        reference_power: 20.0,
+        path_loss_exponent: 2.0,
+      },
+      (PathLossModel::LogDistance, Band::Ghz2) => BandPathLoss {
+        reference_power: -40.0,
+        path_loss_exponent: 3.0,
+      },
+      (PathLossModel::LogDistance, Band::Ghz5 | Band::Ghz6) => BandPathLoss {
+        reference_power: -47.0,
+        path_loss_exponent: 3.5,
+      },
+    }
+  }
+
+  /// Meters to a transmitter heard at `signal` dBm on `frequency` MHz.
+  pub fn estimate_distance(&self, frequency: u16, signal: i8) -> Option<f64> {
+    let band = Band::from_frequency(frequency)?;
+    let BandPathLoss {
+      reference_power,
+      path_loss_exponent,
+    } = self.band_path_loss(band);
+
+    let path_loss = reference_power - f64::from(signal);
+
+    let exponent = match self.model {
+      PathLossModel::FreeSpace => (path_loss - 20.0 * f64::from(frequency).log10() + 27.55) / 20.0,
+      PathLossModel::LogDistance => path_loss / (10.0 * path_loss_exponent),
+    };
+
+    Some(10.0_f64.powf(exponent))
+  }
+}
+
+#[test]
+fn test_estimate_distance() {
+  let log_distance = DistanceConfig {
+    model: PathLossModel::LogDistance,
+    bands: [(
+      Band::Ghz2,
+      BandPathLoss {
+        reference_power: -40.0,
+        path_loss_exponent: 2.0,
+      },
+    )]
+    .into_iter()
+    .collect(),
+  };
+  let distance = log_distance.estimate_distance(2437, -60).unwrap();
+  assert!((distance - 10.0).abs() < 1e-9);
+
+  // 20dBm at 2400MHz loses ~40dB in the first meter
+  let free_space = DistanceConfig {
+    model: PathLossModel::FreeSpace,
+    bands: HashMap::new(),
+  };
+  let distance = free_space.estimate_distance(2400, -20).unwrap();
+  assert!((distance - 1.0).abs() < 0.01);
+
+  assert!(free_space.estimate_distance(900, -20).is_none());
+}
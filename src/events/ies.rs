@@ -0,0 +1,219 @@
+//! 802.11 information elements, read straight from frame bytes for the parts
+//! `ieee80211` doesn't parse.
+
+use serde::{Deserialize, Serialize};
+
+pub const IE_SSID: u8 = 0;
+pub const IE_SUPPORTED_RATES: u8 = 1;
+pub const IE_DS_PARAMETER_SET: u8 = 3;
+pub const IE_HT_CAPABILITIES: u8 = 45;
+pub const IE_RSN: u8 = 48;
+pub const IE_EXTENDED_SUPPORTED_RATES: u8 = 50;
+pub const IE_EXTENDED_CAPABILITIES: u8 = 127;
+pub const IE_VHT_CAPABILITIES: u8 = 191;
+pub const IE_EXTENSION: u8 = 255;
+pub const IE_VENDOR_SPECIFIC: u8 = 221;
+
+// frame control, duration, 3 addresses, sequence control
+const MANAGEMENT_HEADER_LEN: usize = 24;
+// timestamp, beacon interval, capability info
+const BEACON_FIXED_LEN: usize = 12;
+// capability info, listen interval
+const ASSOCIATION_REQUEST_FIXED_LEN: usize = 4;
+// and the current access point
+const REASSOCIATION_REQUEST_FIXED_LEN: usize = 10;
+
+const CAPABILITY_PRIVACY: u16 = 0x0010;
+
+const WPA_OUI_TYPE: [u8; 4] = [0x00, 0x50, 0xf2, 0x01];
+const RSN_OUI: [u8; 3] = [0x00, 0x0f, 0xac];
+const AKM_SAE: u8 = 8;
+const AKM_OWE: u8 = 18;
+
+/// (id, data) of each element, stops at the first truncated one.
+pub fn information_elements(mut bytes: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+  std::iter::from_fn(move || {
+    let (&id, rest) = bytes.split_first()?;
+    let (&len, rest) = rest.split_first()?;
+    let len = usize::from(len);
+    if rest.len() < len {
+      return None;
+    }
+
+    let (data, rest) = rest.split_at(len);
+    bytes = rest;
+    Some((id, data))
+  })
+}
+
+/// Elements of a probe request.
+pub fn probe_request_elements(frame: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+  information_elements(frame.get(MANAGEMENT_HEADER_LEN..).unwrap_or_default())
+}
+
+/// Elements of an association or reassociation request.
+pub fn association_request_elements(
+  frame: &[u8],
+  reassociation: bool,
+) -> impl Iterator<Item = (u8, &[u8])> {
+  let fixed_len = if reassociation {
+    REASSOCIATION_REQUEST_FIXED_LEN
+  } else {
+    ASSOCIATION_REQUEST_FIXED_LEN
+  };
+
+  information_elements(
+    frame
+      .get(MANAGEMENT_HEADER_LEN + fixed_len..)
+      .unwrap_or_default(),
+  )
+}
+
+/// Hash of what a device puts in its probe requests, the same across its
+/// random addresses and usually across devices of the same model. Leaves out
+/// the SSID and channel, and vendor elements past their OUI and type.
+pub fn ie_fingerprint<'a>(elements: impl Iterator<Item = (u8, &'a [u8])>) -> u32 {
+  let mut hasher = crc32fast::Hasher::new();
+
+  for (id, data) in elements {
+    match id {
+      IE_SSID | IE_DS_PARAMETER_SET => continue,
+
+      IE_SUPPORTED_RATES
+      | IE_EXTENDED_SUPPORTED_RATES
+      | IE_HT_CAPABILITIES
+      | IE_EXTENDED_CAPABILITIES
+      | IE_VHT_CAPABILITIES
+      | IE_EXTENSION => {
+        hasher.update(&[id]);
+        hasher.update(data);
+      }
+
+      IE_VENDOR_SPECIFIC => {
+        hasher.update(&[id]);
+        hasher.update(&data[..data.len().min(4)]);
+      }
+
+      _ => hasher.update(&[id]),
+    }
+  }
+
+  hasher.finalize()
+}
+
+/// Capability info and elements of a beacon or probe response.
+pub fn beacon_elements(frame: &[u8]) -> Option<(u16, impl Iterator<Item = (u8, &[u8])>)> {
+  let body = frame.get(MANAGEMENT_HEADER_LEN..)?;
+  let fixed = body.get(..BEACON_FIXED_LEN)?;
+  let capability_info = u16::from_le_bytes([fixed[10], fixed[11]]);
+
+  Some((
+    capability_info,
+    information_elements(&body[BEACON_FIXED_LEN..]),
+  ))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+  Open,
+  /// opportunistic wireless encryption, open but encrypted
+  Owe,
+  Wep,
+  Wpa,
+  Wpa2,
+  Wpa3,
+}
+
+impl Security {
+  /// From a beacon or probe response.
+  pub fn from_beacon(frame: &[u8]) -> Option<Self> {
+    let (capability_info, elements) = beacon_elements(frame)?;
+
+    let mut security = if capability_info & CAPABILITY_PRIVACY == 0 {
+      Self::Open
+    } else {
+      Self::Wep
+    };
+
+    for (id, data) in elements {
+      match id {
+        IE_RSN => return Some(rsn_security(data).unwrap_or(Self::Wpa2)),
+        IE_VENDOR_SPECIFIC if data.starts_with(&WPA_OUI_TYPE) => security = Self::Wpa,
+        _ => {}
+      }
+    }
+
+    Some(security)
+  }
+
+  pub fn is_open(self) -> bool {
+    matches!(self, Self::Open | Self::Owe)
+  }
+}
+
+/// Picks the best AKM suite in an RSN element.
+fn rsn_security(data: &[u8]) -> Option<Security> {
+  // version, group cipher
+  let rest = data.get(6..)?;
+
+  let pairwise_count = usize::from(u16::from_le_bytes([*rest.first()?, *rest.get(1)?]));
+  let rest = rest.get(2 + 4 * pairwise_count..)?;
+
+  let akm_count = usize::from(u16::from_le_bytes([*rest.first()?, *rest.get(1)?]));
+  let akms = rest.get(2..2 + 4 * akm_count)?;
+
+  let mut security = Security::Wpa2;
+  for akm in akms.chunks_exact(4) {
+    if akm[..3] != RSN_OUI {
+      continue;
+    }
+
+    match akm[3] {
+      AKM_SAE => security = Security::Wpa3,
+      AKM_OWE if security == Security::Wpa2 => security = Security::Owe,
+      _ => {}
+    }
+  }
+
+  Some(security)
+}
+
+#[test]
+fn test_security_from_beacon() {
+  let beacon = |capability_info: u16, elements: &[u8]| {
+    let mut bytes = vec![0x80, 0x00];
+    bytes.resize(MANAGEMENT_HEADER_LEN + 10, 0);
+    bytes.extend_from_slice(&capability_info.to_le_bytes());
+    bytes.extend_from_slice(&[IE_SSID, 4, b't', b'e', b's', b't']);
+    bytes.extend_from_slice(elements);
+    bytes
+  };
+
+  assert_eq!(
+    Security::from_beacon(&beacon(0x0001, &[])),
+    Some(Security::Open)
+  );
+  assert_eq!(
+    Security::from_beacon(&beacon(0x0011, &[])),
+    Some(Security::Wep)
+  );
+
+  // CCMP pairwise, PSK and SAE AKMs
+  let rsn = [
+    IE_RSN, 24, 1, 0, 0x00, 0x0f, 0xac, 4, 1, 0, 0x00, 0x0f, 0xac, 4, 2, 0, 0x00, 0x0f, 0xac, 2,
+    0x00, 0x0f, 0xac, 8, 0, 0,
+  ];
+  assert_eq!(
+    Security::from_beacon(&beacon(0x0011, &rsn)),
+    Some(Security::Wpa3)
+  );
+
+  let wpa = [IE_VENDOR_SPECIFIC, 4, 0x00, 0x50, 0xf2, 0x01];
+  assert_eq!(
+    Security::from_beacon(&beacon(0x0011, &wpa)),
+    Some(Security::Wpa)
+  );
+
+  assert_eq!(Security::from_beacon(&[0x80, 0x00]), None);
+}
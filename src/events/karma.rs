@@ -0,0 +1,95 @@
+use super::{format_mac, Alert, AlertKind, Severity};
+use ieee80211::MacAddress;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct KarmaConfig {
+  /// distinct probed-for SSIDs one BSSID may answer before alerting
+  pub ssids: usize,
+}
+
+impl Default for KarmaConfig {
+  fn default() -> Self {
+    // a real AP answers for its own SSID, maybe a couple with one BSSID
+    Self { ssids: 3 }
+  }
+}
+
+/// Karma/MANA style APs that answer probe requests with whatever SSID the
+/// client asked for.
+pub struct KarmaDetector {
+  config: KarmaConfig,
+  // bssid -> answered SSIDs that a client had probed for
+  answered: HashMap<MacAddress, HashSet<Vec<u8>>>,
+  alerted: HashSet<MacAddress>,
+}
+
+impl KarmaDetector {
+  pub fn new(config: KarmaConfig) -> Self {
+    Self {
+      config,
+      answered: HashMap::new(),
+      alerted: HashSet::new(),
+    }
+  }
+
+  /// A probe response for `ssid` that its receiver had probed for.
+  pub fn probe_response(&mut self, bssid: MacAddress, ssid: &[u8]) -> Option<Alert> {
+    if self.alerted.contains(&bssid) {
+      return None;
+    }
+
+    let ssids = self.answered.entry(bssid).or_default();
+    ssids.insert(ssid.to_vec());
+    if ssids.len() <= self.config.ssids {
+      return None;
+    }
+
+    let mut ssids: Vec<String> = ssids
+      .iter()
+      .map(|ssid| String::from_utf8_lossy(ssid).to_string())
+      .collect();
+    ssids.sort();
+
+    self.alerted.insert(bssid);
+    self.answered.remove(&bssid);
+
+    let message = format!(
+      "{} answers probes for {} different SSIDs, possible karma attack",
+      format_mac(bssid),
+      ssids.len()
+    );
+
+    Some(Alert::new(
+      Severity::Critical,
+      AlertKind::Karma { bssid, ssids },
+      message,
+    ))
+  }
+}
+
+#[test]
+fn test_karma() {
+  let bssid = MacAddress::from_bytes(&[0x02, 0, 0, 0, 0, 0xaa]).unwrap();
+
+  let mut detector = KarmaDetector::new(KarmaConfig::default());
+
+  // the same SSID over and over is fine
+  for _ in 0..10 {
+    assert!(detector.probe_response(bssid, b"home").is_none());
+  }
+
+  assert!(detector.probe_response(bssid, b"cafe").is_none());
+  assert!(detector.probe_response(bssid, b"airport").is_none());
+
+  let alert = detector.probe_response(bssid, b"hotel").unwrap();
+  assert!(matches!(
+    &alert.kind,
+    AlertKind::Karma { ssids, .. } if ssids.len() == 4
+  ));
+
+  // only once
+  assert!(detector.probe_response(bssid, b"office").is_none());
+}
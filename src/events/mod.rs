@@ -1,10 +1,32 @@
+mod airtime;
+mod alert;
+mod beacon_flood;
+mod deauth;
+mod dedup;
+mod device_fingerprint;
+mod distance;
+mod ies;
+mod karma;
+mod oui;
+mod phy;
+mod position;
+mod random_mac;
+mod roaming;
+mod rogue_ap;
+mod signal_history;
 pub mod store;
+mod traffic;
 mod util;
+mod watchlist;
 
-pub use self::{store::*, util::*};
+pub use self::{
+  airtime::*, alert::*, beacon_flood::*, deauth::*, dedup::*, device_fingerprint::*, distance::*,
+  ies::*, karma::*, oui::*, phy::*, position::*, random_mac::*, roaming::*, rogue_ap::*,
+  signal_history::*, store::*, traffic::*, util::*, watchlist::*,
+};
 use crate::{
   error::{err_msg, Result},
-  packet_capture::FrameWithRadiotap,
+  packet_capture::{CaptureStats, FrameWithRadiotap},
 };
 use ieee80211::{
   BeaconFixedParametersTrait, ControlFrameTrait, DataFrameTrait, FrameLayer, FrameSubtype,
@@ -12,6 +34,7 @@ use ieee80211::{
   TaggedParametersTrait,
 };
 use log::warn;
+use std::{sync::Arc, time::Duration};
 
 pub fn handle_frame(
   store: &mut Store,
@@ -19,6 +42,33 @@ pub fn handle_frame(
 ) -> Result<Vec<Event>> {
   let frame = &frame_with_radiotap.frame;
 
+  if let Some(sensor) = &frame_with_radiotap.sensor {
+    if !frame_with_radiotap.bad_fcs && handle_duplicate(store, frame_with_radiotap, sensor) {
+      store.check_timers();
+      return Ok(store.flush_buffer());
+    }
+  }
+
+  store.count_frame();
+
+  let airtime = get_airtime(frame_with_radiotap);
+
+  if frame_with_radiotap.bad_fcs {
+    // don't trust anything in a corrupted frame, it would add ghost devices
+    let transmitter_address = frame
+      .next_layer()
+      .and_then(|layer| get_transmitter_address(&layer));
+    store.bad_frame(transmitter_address);
+
+    // it still kept the channel busy
+    if let Some((frequency, airtime)) = airtime {
+      store.add_airtime(None, frequency, airtime);
+    }
+
+    store.check_timers();
+    return Ok(store.flush_buffer());
+  }
+
   let receiver_address = frame.receiver_address();
   if !is_broadcast(receiver_address) {
     store.add_address(receiver_address);
@@ -28,14 +78,23 @@ pub fn handle_frame(
     .next_layer()
     .ok_or_else(|| err_msg("frame.next_layer"))?;
 
-  let transmitter_address = match layer {
-    FrameLayer::Management(management_frame) => management_frame.transmitter_address(),
-    FrameLayer::Control(control_frame) => control_frame.transmitter_address(),
-    FrameLayer::Data(data_frame) => data_frame.transmitter_address(),
-  };
+  let transmitter_address = get_transmitter_address(layer);
+
+  store.count_frame_subtype(transmitter_address, frame_subtype_name(frame.bytes()));
+
+  if let Some((frequency, airtime)) = airtime {
+    store.add_airtime(transmitter_address, frequency, airtime);
+  }
 
   if let Some(transmitter_address) = transmitter_address {
     store.add_address(transmitter_address);
+    store.add_traffic(
+      transmitter_address,
+      receiver_address,
+      layer,
+      frame_with_radiotap.length,
+      frame.retry(),
+    );
 
     handle_transmitter(
       store,
@@ -68,6 +127,78 @@ pub fn handle_frame(
   Ok(store.flush_buffer())
 }
 
+/// True if another sensor already reported the frame, then only its signal
+/// is kept, for positioning.
+fn handle_duplicate(
+  store: &mut Store,
+  frame_with_radiotap: &FrameWithRadiotap,
+  sensor: &Arc<str>,
+) -> bool {
+  let frame = &frame_with_radiotap.frame;
+  let transmitter_address = frame
+    .next_layer()
+    .and_then(|layer| get_transmitter_address(&layer));
+
+  if !store.is_duplicate_frame(
+    sensor,
+    transmitter_address,
+    sequence_number(frame.bytes()),
+    frame.bytes(),
+  ) {
+    return false;
+  }
+
+  if let (Some(transmitter_address), Some(signal)) =
+    (transmitter_address, frame_with_radiotap.signal)
+  {
+    store.sensor_signal(transmitter_address, sensor, signal);
+  }
+
+  true
+}
+
+fn get_transmitter_address(layer: &FrameLayer) -> Option<MacAddress> {
+  match layer {
+    FrameLayer::Management(management_frame) => management_frame.transmitter_address(),
+    FrameLayer::Control(control_frame) => control_frame.transmitter_address(),
+    FrameLayer::Data(data_frame) => data_frame.transmitter_address(),
+  }
+}
+
+/// frequency, airtime
+fn get_airtime(frame_with_radiotap: &FrameWithRadiotap) -> Option<(Option<u16>, Duration)> {
+  let radiotap = frame_with_radiotap.radiotap.as_ref()?;
+  let phy_info = PhyInfo::from_radiotap(radiotap);
+
+  let flags = radiotap.flags.as_ref();
+  let airtime = frame_airtime(
+    &phy_info,
+    frame_with_radiotap.length,
+    flags.map_or(false, |flags| flags.preamble),
+    flags.map_or(false, |flags| flags.sgi),
+  )?;
+
+  Some((phy_info.frequency, airtime))
+}
+
+pub fn handle_capture_stats(store: &mut Store, stats: CaptureStats) -> Vec<Event> {
+  store.capture_stats(stats);
+
+  store.flush_buffer()
+}
+
+pub fn handle_sensor_stats(store: &mut Store, name: &str, stats: CaptureStats) -> Vec<Event> {
+  store.sensor_stats(name, stats);
+
+  store.flush_buffer()
+}
+
+pub fn handle_sensor_status(store: &mut Store, name: &str, connected: bool) -> Vec<Event> {
+  store.sensor_status(name, connected);
+
+  store.flush_buffer()
+}
+
 fn handle_transmitter(
   store: &mut Store,
   frame_with_radiotap: &FrameWithRadiotap,
@@ -93,15 +224,21 @@ fn handle_transmitter(
       match management_frame_layer {
         ManagementFrameLayer::Beacon(ref beacon_frame) => {
           let tagged_parameters = beacon_frame.tagged_parameters()?;
+          let ssid = tagged_parameters.ssid().ok_or_else(|| err_msg("ssid"))?;
 
+          store.beacon(
+            transmitter_address,
+            ssid,
+            sequence_number(frame.bytes()),
+            frame_with_radiotap.signal,
+            frame_with_radiotap.timestamp,
+          );
           store.access_point(
             transmitter_address,
             AccessPointInfo {
-              ssid: tagged_parameters
-                .ssid()
-                .ok_or_else(|| err_msg("ssid"))?
-                .to_vec(),
+              ssid: ssid.to_vec(),
               channel: tagged_parameters.channel(),
+              security: Security::from_beacon(frame.bytes()),
             },
           );
 
@@ -111,14 +248,16 @@ fn handle_transmitter(
         ManagementFrameLayer::ProbeResponse(ref probe_response_frame) => {
           let tagged_parameters = probe_response_frame.tagged_parameters()?;
 
-          store.access_point(
+          store.probe_response(
             transmitter_address,
+            receiver_address,
             AccessPointInfo {
               ssid: tagged_parameters
                 .ssid()
                 .ok_or_else(|| err_msg("ssid"))?
                 .to_vec(),
               channel: tagged_parameters.channel(),
+              security: Security::from_beacon(frame.bytes()),
             },
           );
         }
@@ -130,6 +269,15 @@ fn handle_transmitter(
           if !ssid.is_empty() {
             store.probe_request(transmitter_address, ssid.to_vec());
           }
+
+          if is_locally_administered(transmitter_address) {
+            store.random_probe_request(
+              transmitter_address,
+              ie_fingerprint(probe_request_elements(frame.bytes())),
+              sequence_number(frame.bytes()),
+              Some(ssid).filter(|ssid| !ssid.is_empty()),
+            );
+          }
         }
 
         _ => {}
@@ -137,12 +285,42 @@ fn handle_transmitter(
     }
   }
 
-  store.update_rate(transmitter_address);
+  // stations describe themselves in these
+  let station_elements: Option<Vec<(u8, &[u8])>> = match frame.subtype() {
+    FrameSubtype::Management(ManagementSubtype::ProbeRequest) => {
+      Some(probe_request_elements(frame.bytes()).collect())
+    }
+    FrameSubtype::Management(ManagementSubtype::AssociationRequest) => {
+      Some(association_request_elements(frame.bytes(), false).collect())
+    }
+    FrameSubtype::Management(ManagementSubtype::ReassociationRequest) => {
+      Some(association_request_elements(frame.bytes(), true).collect())
+    }
+    _ => None,
+  };
+  if let Some(elements) = station_elements {
+    store.device_fingerprint(transmitter_address, &elements);
+  }
+
+  store.update_rate(transmitter_address, frame.retry());
 
   if let Some(radiotap) = &frame_with_radiotap.radiotap {
-    if let Some(signal) = &radiotap.antenna_signal {
-      store.update_signal(transmitter_address, signal.value);
+    if let Some(signal) = frame_with_radiotap.signal {
+      store.update_signal(
+        transmitter_address,
+        frame_with_radiotap.sensor.as_deref(),
+        signal,
+        frame_with_radiotap.antenna_signals.clone(),
+      );
     }
+
+    // management and control frames go out at basic rates
+    let is_data = matches!(layer, FrameLayer::Data(_));
+    store.update_phy(
+      transmitter_address,
+      PhyInfo::from_radiotap(radiotap),
+      is_data,
+    );
   }
 
   // store.update_loss(transmitter_address, receiver_address, &layer);
@@ -181,6 +359,31 @@ fn check_association(
             receiver_address,
             ConnectionType::Authentication,
           );
+
+          // the STA is moving to the AP it sends these to
+          if matches!(
+            subtype,
+            ManagementSubtype::AssociationRequest | ManagementSubtype::ReassociationRequest
+          ) {
+            let reassociation = matches!(subtype, ManagementSubtype::ReassociationRequest);
+            let ssid = association_request_elements(frame.bytes(), reassociation)
+              .find(|(id, _data)| *id == IE_SSID)
+              .map(|(_id, data)| data);
+
+            store.association_request(transmitter_address, receiver_address, ssid);
+          }
+
+          // and only there once the AP accepts
+          if matches!(
+            subtype,
+            ManagementSubtype::AssociationResponse | ManagementSubtype::ReassociationResponse
+          ) {
+            store.association_response(
+              transmitter_address,
+              receiver_address,
+              association_status(frame.bytes()) == Some(0),
+            );
+          }
         }
 
         ManagementSubtype::Disassociate | ManagementSubtype::Deauthentication => {
@@ -188,6 +391,12 @@ fn check_association(
           // Disassociation is from STA
           // Deauthentication is from AP
 
+          store.deauth(
+            transmitter_address,
+            receiver_address,
+            frame_with_radiotap.timestamp,
+          );
+
           store.change_connection(
             transmitter_address,
             receiver_address,
@@ -205,6 +414,8 @@ fn check_association(
 
   // if two nodes are communicating
   if is_associated {
+    store.station_data(transmitter_address, receiver_address);
+
     store.change_connection(
       transmitter_address,
       receiver_address,
@@ -0,0 +1,158 @@
+//! Vendor names from the IEEE MA-L, MA-M and MA-S registries.
+
+use crate::error::{err_msg, Result};
+use log::info;
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path, sync::OnceLock};
+
+// same data as the frontend, made by web/create_oui.js
+const EMBEDDED: &str = include_str!("../../web/oui_data.json");
+
+// hex digits of MA-S, MA-M and MA-L prefixes, longest first
+const PREFIX_LENGTHS: [usize; 3] = [9, 7, 6];
+
+static DATABASE: OnceLock<OuiDatabase> = OnceLock::new();
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EmbeddedEntry {
+  Vendor(String),
+  // an MA-L split into smaller blocks, by longer prefix
+  Blocks(HashMap<String, String>),
+}
+
+#[derive(Default)]
+struct OuiDatabase {
+  // uppercase hex prefix -> vendor
+  vendors: HashMap<String, String>,
+}
+
+impl OuiDatabase {
+  fn embedded() -> Self {
+    let entries: HashMap<String, EmbeddedEntry> =
+      serde_json::from_str(EMBEDDED).expect("embedded oui data");
+
+    let mut database = Self::default();
+    for (prefix, entry) in entries {
+      match entry {
+        EmbeddedEntry::Vendor(vendor) => {
+          database.vendors.insert(prefix, vendor);
+        }
+        EmbeddedEntry::Blocks(blocks) => database.vendors.extend(blocks),
+      }
+    }
+
+    database
+  }
+
+  /// Rows of an IEEE `oui.csv`, `mam.csv` or `oui36.csv`, replacing what's
+  /// there. Returns how many were added.
+  fn add_csv(&mut self, text: &str) -> usize {
+    let mut added = 0;
+
+    for line in text.lines() {
+      let fields = csv_fields(line);
+      let (Some(assignment), Some(vendor)) = (fields.get(1), fields.get(2)) else {
+        continue;
+      };
+
+      // the header and anything else that isn't a prefix
+      let assignment = assignment.to_uppercase();
+      if !PREFIX_LENGTHS.contains(&assignment.len())
+        || !assignment.chars().all(|c| c.is_ascii_hexdigit())
+      {
+        continue;
+      }
+
+      self.vendors.insert(assignment, vendor.trim().to_string());
+      added += 1;
+    }
+
+    added
+  }
+
+  fn lookup(&self, mac: &[u8]) -> Option<&str> {
+    let hex: String = mac.iter().map(|byte| format!("{:02X}", byte)).collect();
+
+    PREFIX_LENGTHS
+      .iter()
+      .filter_map(|len| hex.get(..*len))
+      .find_map(|prefix| self.vendors.get(prefix))
+      .map(String::as_str)
+  }
+}
+
+/// Splits a CSV line, with `"quoted, fields"` and `""` for a quote in one.
+fn csv_fields(line: &str) -> Vec<String> {
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut quoted = false;
+
+  let mut chars = line.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '"' if quoted && chars.peek() == Some(&'"') => {
+        chars.next();
+        field.push('"');
+      }
+      '"' => quoted = !quoted,
+      ',' if !quoted => fields.push(std::mem::take(&mut field)),
+      _ => field.push(c),
+    }
+  }
+  fields.push(field);
+
+  fields
+}
+
+/// Parses the embedded vendors and newer IEEE CSVs over them. Slow, so call it
+/// at startup before the first `vendor`.
+pub fn load_oui_csv<P: AsRef<Path>>(paths: &[P]) -> Result<()> {
+  let mut database = OuiDatabase::embedded();
+
+  for path in paths {
+    let path = path.as_ref();
+    let added = database.add_csv(&fs::read_to_string(path)?);
+    if added == 0 {
+      return Err(err_msg(format!("no vendors in {:?}", path)));
+    }
+    info!("loaded {} vendors from {:?}", added, path);
+  }
+
+  DATABASE
+    .set(database)
+    .map_err(|_| err_msg("vendors already loaded"))
+}
+
+/// Vendor of a MAC address, `None` for random addresses and unknown ones.
+/// Only parses the embedded vendors itself if `load_oui_csv` wasn't called.
+pub fn vendor(mac: &[u8]) -> Option<&'static str> {
+  DATABASE.get_or_init(OuiDatabase::embedded).lookup(mac)
+}
+
+#[test]
+fn test_oui() {
+  let mut database = OuiDatabase::embedded();
+
+  assert_eq!(database.lookup(&[0x10, 0x00, 0x90, 1, 2, 3]), Some("HP"));
+  // an MA-M block
+  assert_eq!(
+    database.lookup(&[0x10, 0x07, 0x23, 0x01, 2, 3]),
+    Some("RippleTek")
+  );
+  assert_eq!(database.lookup(&[0x02, 0, 0, 0, 0, 1]), None);
+
+  let csv = "Registry,Assignment,Organization Name,Organization Address\n\
+    MA-L,100090,\"Hewlett Packard, Inc.\",\"1501 Page Mill Road Palo Alto CA US 94304 \"\n\
+    MA-S,70B3D5123,\"Some \"\"Quoted\"\" Vendor\",Somewhere\n";
+  assert_eq!(database.add_csv(csv), 2);
+
+  assert_eq!(
+    database.lookup(&[0x10, 0x00, 0x90, 1, 2, 3]),
+    Some("Hewlett Packard, Inc.")
+  );
+  assert_eq!(
+    database.lookup(&[0x70, 0xb3, 0xd5, 0x12, 0x34, 0x56]),
+    Some("Some \"Quoted\" Vendor")
+  );
+}
@@ -0,0 +1,107 @@
+use radiotap::Radiotap;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Band {
+  #[serde(rename = "2.4GHz")]
+  Ghz2,
+  #[serde(rename = "5GHz")]
+  Ghz5,
+  #[serde(rename = "6GHz")]
+  Ghz6,
+}
+
+impl Band {
+  pub fn from_frequency(frequency: u16) -> Option<Self> {
+    match frequency {
+      2400..=2500 => Some(Self::Ghz2),
+      5000..=5924 => Some(Self::Ghz5),
+      5925..=7125 => Some(Self::Ghz6),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum PhyKind {
+  /// 802.11a/b/g
+  Legacy,
+  /// 802.11n
+  Ht,
+  /// 802.11ac
+  Vht,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PhyInfo {
+  pub kind: Option<PhyKind>,
+  /// Mbps
+  pub rate: Option<f32>,
+  pub mcs: Option<u8>,
+  pub spatial_streams: Option<u8>,
+
+  pub band: Option<Band>,
+  /// MHz
+  pub frequency: Option<u16>,
+  pub antenna: Option<u8>,
+  /// signal - noise, dB
+  pub snr: Option<i16>,
+}
+
+impl PhyInfo {
+  pub fn from_radiotap(radiotap: &Radiotap) -> Self {
+    let frequency = radiotap.channel.as_ref().map(|channel| channel.freq);
+
+    let mut info = Self {
+      kind: None,
+      rate: None,
+      mcs: None,
+      spatial_streams: None,
+
+      band: frequency.and_then(Band::from_frequency),
+      frequency,
+      antenna: radiotap.antenna.as_ref().map(|antenna| antenna.value),
+      snr: match (&radiotap.antenna_signal, &radiotap.antenna_noise) {
+        (Some(signal), Some(noise)) => Some(i16::from(signal.value) - i16::from(noise.value)),
+        _ => None,
+      },
+    };
+
+    if let Some(vht) = &radiotap.vht {
+      // only single user frames are interesting here
+      if let Some(user) = vht.users.iter().flatten().next() {
+        info.kind = Some(PhyKind::Vht);
+        info.rate = user.datarate;
+        info.mcs = Some(user.index);
+        info.spatial_streams = Some(user.nss);
+      }
+    } else if let Some(mcs) = &radiotap.mcs {
+      info.kind = Some(PhyKind::Ht);
+      info.rate = mcs.datarate;
+      info.mcs = mcs.index;
+      // HT MCS 0-7 are 1 stream, 8-15 are 2 streams, etc
+      info.spatial_streams = mcs
+        .index
+        .filter(|index| *index < 32)
+        .map(|index| index / 8 + 1);
+    } else if let Some(rate) = &radiotap.rate {
+      info.kind = Some(PhyKind::Legacy);
+      info.rate = Some(rate.value);
+      info.spatial_streams = Some(1);
+    }
+
+    info
+  }
+
+  /// Keeps the rate fields from `last`, used for frames that are always
+  /// sent at basic rates and say nothing about the link.
+  pub fn with_rate_from(self, last: &PhyInfo) -> Self {
+    Self {
+      kind: last.kind,
+      rate: last.rate,
+      mcs: last.mcs,
+      spatial_streams: last.spatial_streams,
+      ..self
+    }
+  }
+}
@@ -0,0 +1,193 @@
+use super::{DistanceConfig, SensorSignal};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PositionMethod {
+  /// average of sensor positions weighted by received power
+  #[default]
+  WeightedCentroid,
+  /// least squares fit of distances from the path loss model, needs at
+  /// least 3 sensors and falls back to weighted-centroid
+  Trilateration,
+}
+
+/// meters, in whatever frame the sensor positions use
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Point {
+  pub x: f64,
+  pub y: f64,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PositioningConfig {
+  #[serde(default)]
+  pub method: PositionMethod,
+  /// by sensor name
+  pub sensors: HashMap<String, Point>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Position {
+  #[serde(flatten)]
+  pub point: Point,
+  /// how many sensors were used
+  pub sensors: usize,
+  pub method: PositionMethod,
+}
+
+const MIN_CENTROID_SENSORS: usize = 2;
+const MIN_TRILATERATION_SENSORS: usize = 3;
+
+impl PositioningConfig {
+  /// `frequency` and `distance_config` are only needed for trilateration.
+  pub fn estimate(
+    &self,
+    signals: &[SensorSignal],
+    frequency: Option<u16>,
+    distance_config: &DistanceConfig,
+  ) -> Option<Position> {
+    let readings: Vec<(Point, i8)> = signals
+      .iter()
+      .filter_map(|reading| Some((*self.sensors.get(&reading.sensor)?, reading.signal)))
+      .collect();
+
+    if self.method == PositionMethod::Trilateration && readings.len() >= MIN_TRILATERATION_SENSORS {
+      if let Some(frequency) = frequency {
+        let distances: Option<Vec<(Point, f64)>> = readings
+          .iter()
+          .map(|(point, signal)| {
+            Some((
+              *point,
+              distance_config.estimate_distance(frequency, *signal)?,
+            ))
+          })
+          .collect();
+
+        if let Some(point) = distances.as_deref().and_then(trilaterate) {
+          return Some(Position {
+            point,
+            sensors: readings.len(),
+            method: PositionMethod::Trilateration,
+          });
+        }
+      }
+    }
+
+    if readings.len() < MIN_CENTROID_SENSORS {
+      return None;
+    }
+
+    Some(Position {
+      point: weighted_centroid(&readings),
+      sensors: readings.len(),
+      method: PositionMethod::WeightedCentroid,
+    })
+  }
+}
+
+fn weighted_centroid(readings: &[(Point, i8)]) -> Point {
+  let mut x = 0.0;
+  let mut y = 0.0;
+  let mut total_weight = 0.0;
+
+  for (point, signal) in readings {
+    // mW, so a sensor 10dB stronger counts 10 times as much
+    let weight = 10.0_f64.powf(f64::from(*signal) / 10.0);
+
+    x += point.x * weight;
+    y += point.y * weight;
+    total_weight += weight;
+  }
+
+  Point {
+    x: x / total_weight,
+    y: y / total_weight,
+  }
+}
+
+/// Subtracting the last circle's equation from the others gives a linear
+/// system, solved with the normal equations. `None` if the sensors are on a
+/// line.
+fn trilaterate(distances: &[(Point, f64)]) -> Option<Point> {
+  let (last, last_distance) = distances.last()?;
+
+  // A^T A and A^T b
+  let (mut aa, mut ab, mut bb) = (0.0, 0.0, 0.0);
+  let (mut ac, mut bc) = (0.0, 0.0);
+
+  for (point, distance) in &distances[..distances.len() - 1] {
+    let a = 2.0 * (last.x - point.x);
+    let b = 2.0 * (last.y - point.y);
+    let c = distance * distance - last_distance * last_distance - point.x * point.x
+      + last.x * last.x
+      - point.y * point.y
+      + last.y * last.y;
+
+    aa += a * a;
+    ab += a * b;
+    bb += b * b;
+    ac += a * c;
+    bc += b * c;
+  }
+
+  let determinant = aa * bb - ab * ab;
+  if determinant.abs() < 1e-9 {
+    return None;
+  }
+
+  Some(Point {
+    x: (bb * ac - ab * bc) / determinant,
+    y: (aa * bc - ab * ac) / determinant,
+  })
+}
+
+#[test]
+fn test_trilaterate() {
+  let target = Point { x: 3.0, y: 4.0 };
+  let sensors = [
+    Point { x: 0.0, y: 0.0 },
+    Point { x: 10.0, y: 0.0 },
+    Point { x: 0.0, y: 10.0 },
+    Point { x: 10.0, y: 10.0 },
+  ];
+
+  let distances: Vec<(Point, f64)> = sensors
+    .iter()
+    .map(|point| (*point, (point.x - target.x).hypot(point.y - target.y)))
+    .collect();
+
+  let point = trilaterate(&distances).unwrap();
+  assert!((point.x - target.x).abs() < 1e-6);
+  assert!((point.y - target.y).abs() < 1e-6);
+
+  // all on a line
+  let line: Vec<(Point, f64)> = (0..3)
+    .map(|i| {
+      (
+        Point {
+          x: f64::from(i),
+          y: 0.0,
+        },
+        1.0,
+      )
+    })
+    .collect();
+  assert!(trilaterate(&line).is_none());
+}
+
+#[test]
+fn test_weighted_centroid() {
+  let a = Point { x: 0.0, y: 0.0 };
+  let b = Point { x: 10.0, y: 0.0 };
+
+  let point = weighted_centroid(&[(a, -50), (b, -50)]);
+  assert!((point.x - 5.0).abs() < 1e-9);
+
+  // 10dB stronger at a is 10 times the weight
+  let point = weighted_centroid(&[(a, -40), (b, -50)]);
+  assert!((point.x - 10.0 / 11.0).abs() < 1e-9);
+}
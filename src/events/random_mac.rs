@@ -0,0 +1,261 @@
+use ieee80211::MacAddress;
+use std::{
+  collections::{HashMap, HashSet},
+  time::{Duration, Instant},
+};
+
+// forget random addresses not heard from in this long
+const ADDRESS_TIMEOUT: Duration = Duration::from_secs(300);
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(10);
+// a new address continuing an old one's sequence numbers
+const MAX_ROTATION_GAP: Duration = Duration::from_secs(10);
+const MAX_SEQUENCE_GAP: u16 = 64;
+// one common SSID isn't enough, everyone probes for the office network
+const MIN_SHARED_SSIDS: usize = 2;
+
+struct RandomAddress {
+  fingerprint: u32,
+  ssids: HashSet<Vec<u8>>,
+  last_sequence_number: Option<u16>,
+  last_seen: Instant,
+}
+
+/// Guesses which random addresses belong to the same device, from their
+/// probe requests.
+pub struct RandomMacCorrelator {
+  addresses: HashMap<MacAddress, RandomAddress>,
+  group_of: HashMap<MacAddress, usize>,
+  groups: HashMap<usize, Vec<MacAddress>>,
+  next_group: usize,
+  next_expire: Instant,
+}
+
+impl Default for RandomMacCorrelator {
+  fn default() -> Self {
+    Self {
+      addresses: HashMap::new(),
+      group_of: HashMap::new(),
+      groups: HashMap::new(),
+      next_group: 0,
+      next_expire: Instant::now() + EXPIRE_INTERVAL,
+    }
+  }
+}
+
+fn sequence_continues(last: u16, next: u16) -> bool {
+  // 12 bits
+  let gap = next.wrapping_sub(last) & 0x0fff;
+  (1..=MAX_SEQUENCE_GAP).contains(&gap)
+}
+
+impl RandomMacCorrelator {
+  pub fn groups(&self) -> impl Iterator<Item = &Vec<MacAddress>> {
+    self.groups.values()
+  }
+
+  /// Forgets addresses not heard from in a while, and groups left with only
+  /// one address.
+  pub fn expire(&mut self, now: Instant) {
+    if now < self.next_expire {
+      return;
+    }
+    self.next_expire = now + EXPIRE_INTERVAL;
+
+    let mut expired = Vec::new();
+    self.addresses.retain(|mac, address| {
+      let keep = now.duration_since(address.last_seen) < ADDRESS_TIMEOUT;
+      if !keep {
+        expired.push(*mac);
+      }
+      keep
+    });
+
+    for mac in expired {
+      let Some(group) = self.group_of.remove(&mac) else {
+        continue;
+      };
+      let Some(macs) = self.groups.get_mut(&group) else {
+        continue;
+      };
+
+      macs.retain(|other| *other != mac);
+      if macs.len() < 2 {
+        for other in macs.drain(..) {
+          self.group_of.remove(&other);
+        }
+        self.groups.remove(&group);
+      }
+    }
+  }
+
+  /// Returns the group when `mac` joined one or two groups merged.
+  pub fn probe_request(
+    &mut self,
+    now: Instant,
+    mac: MacAddress,
+    fingerprint: u32,
+    sequence_number: Option<u16>,
+    ssid: Option<&[u8]>,
+  ) -> Option<Vec<MacAddress>> {
+    let is_new = !self.addresses.contains_key(&mac);
+
+    // only the first frame of a new address can continue another's sequence
+    let continued: Vec<MacAddress> = match sequence_number {
+      Some(sequence_number) if is_new => self
+        .addresses
+        .iter()
+        .filter(|(_other, address)| {
+          address.fingerprint == fingerprint
+            && now.duration_since(address.last_seen) <= MAX_ROTATION_GAP
+            && address
+              .last_sequence_number
+              .map_or(false, |last| sequence_continues(last, sequence_number))
+        })
+        .map(|(other, _address)| *other)
+        .collect(),
+      _ => Vec::new(),
+    };
+
+    let address = self.addresses.entry(mac).or_insert_with(|| RandomAddress {
+      fingerprint,
+      ssids: HashSet::new(),
+      last_sequence_number: None,
+      last_seen: now,
+    });
+    address.fingerprint = fingerprint;
+    address.last_seen = now;
+    if sequence_number.is_some() {
+      address.last_sequence_number = sequence_number;
+    }
+    let new_ssid = ssid.map_or(false, |ssid| address.ssids.insert(ssid.to_vec()));
+
+    let mut matches = continued;
+    if new_ssid {
+      let address = &self.addresses[&mac];
+      matches.extend(
+        self
+          .addresses
+          .iter()
+          .filter(|(other, other_address)| {
+            **other != mac
+              && other_address.fingerprint == fingerprint
+              && other_address.ssids.intersection(&address.ssids).count() >= MIN_SHARED_SSIDS
+          })
+          .map(|(other, _address)| *other),
+      );
+    }
+
+    let mut changed = false;
+    for other in matches {
+      changed |= self.link(mac, other);
+    }
+
+    if changed {
+      self
+        .group_of
+        .get(&mac)
+        .and_then(|group| self.groups.get(group))
+        .cloned()
+    } else {
+      None
+    }
+  }
+
+  /// Puts both in the same group, false if they already were.
+  fn link(&mut self, mac: MacAddress, other: MacAddress) -> bool {
+    match (
+      self.group_of.get(&mac).copied(),
+      self.group_of.get(&other).copied(),
+    ) {
+      (Some(group), Some(other_group)) if group == other_group => false,
+
+      (Some(group), Some(other_group)) => {
+        let moved = self.groups.remove(&other_group).unwrap_or_default();
+        for moved_mac in &moved {
+          self.group_of.insert(*moved_mac, group);
+        }
+        self.groups.entry(group).or_default().extend(moved);
+        true
+      }
+
+      (Some(group), None) | (None, Some(group)) => {
+        let added = if self.group_of.contains_key(&mac) {
+          other
+        } else {
+          mac
+        };
+        self.group_of.insert(added, group);
+        self.groups.entry(group).or_default().push(added);
+        true
+      }
+
+      (None, None) => {
+        let group = self.next_group;
+        self.next_group += 1;
+
+        self.group_of.insert(mac, group);
+        self.group_of.insert(other, group);
+        self.groups.insert(group, vec![other, mac]);
+        true
+      }
+    }
+  }
+}
+
+#[test]
+fn test_random_mac_correlation() {
+  use super::test_mac;
+
+  let mac: Vec<MacAddress> = (0..6)
+    .map(|last| test_mac([0x02, 0, 0, 0, 0, last]))
+    .collect();
+  let start = Instant::now();
+  let later = |secs| start + Duration::from_secs(secs);
+
+  let mut correlator = RandomMacCorrelator::default();
+
+  // the same phone rotating its address, sequence numbers carry on
+  assert!(correlator
+    .probe_request(start, mac[1], 0xaaaa, Some(4094), None)
+    .is_none());
+  let group = correlator
+    .probe_request(later(2), mac[2], 0xaaaa, Some(3), None)
+    .unwrap();
+  assert_eq!(group, vec![mac[1], mac[2]]);
+
+  // same model, unrelated sequence numbers
+  assert!(correlator
+    .probe_request(later(3), mac[3], 0xaaaa, Some(2000), Some(b"office"))
+    .is_none());
+
+  // a different model probing for the same networks
+  assert!(correlator
+    .probe_request(later(4), mac[4], 0xbbbb, Some(100), Some(b"office"))
+    .is_none());
+  assert!(correlator
+    .probe_request(later(4), mac[4], 0xbbbb, Some(101), Some(b"home"))
+    .is_none());
+
+  // the same phone probing for the same networks as mac[3]
+  assert!(correlator
+    .probe_request(later(5), mac[5], 0xaaaa, Some(3000), Some(b"home"))
+    .is_none());
+  assert!(correlator
+    .probe_request(later(5), mac[3], 0xaaaa, Some(2001), Some(b"home"))
+    .is_none());
+  let group = correlator
+    .probe_request(later(6), mac[5], 0xaaaa, Some(3001), Some(b"office"))
+    .unwrap();
+  assert_eq!(group, vec![mac[3], mac[5]]);
+
+  assert_eq!(correlator.groups().count(), 2);
+
+  // mac[1] went quiet, mac[2] is left on its own
+  correlator.probe_request(later(200), mac[2], 0xaaaa, Some(10), None);
+  correlator.probe_request(later(300), mac[3], 0xaaaa, Some(2002), None);
+  correlator.probe_request(later(300), mac[5], 0xaaaa, Some(3002), None);
+  correlator.expire(later(400));
+  let groups: Vec<&Vec<MacAddress>> = correlator.groups().collect();
+  assert_eq!(groups, vec![&vec![mac[3], mac[5]]]);
+  assert!(!correlator.group_of.contains_key(&mac[2]));
+}
@@ -0,0 +1,224 @@
+use ieee80211::MacAddress;
+use serde::Serialize;
+use std::{
+  collections::HashMap,
+  time::{Duration, Instant},
+};
+
+// the old access point can still have buffered frames for a station that just
+// moved, don't count those as a roam back
+const DATA_ROAM_HOLDOFF: Duration = Duration::from_secs(2);
+// an association request without an answer by then was lost or ignored
+const PENDING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A station moving from one access point to another.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Roam {
+  pub sta: MacAddress,
+  pub from_bssid: MacAddress,
+  pub to_bssid: MacAddress,
+  /// of `to_bssid`, empty if we don't know it
+  pub ssid: Vec<u8>,
+  /// seconds
+  pub duration_on_prev: f64,
+}
+
+struct CurrentAccessPoint {
+  bssid: MacAddress,
+  ssid: Vec<u8>,
+  since: Instant,
+}
+
+// asked to (re)associate, not accepted yet
+struct PendingAssociation {
+  bssid: MacAddress,
+  ssid: Option<Vec<u8>>,
+  time: Instant,
+}
+
+/// Which access point each station is on, and how often that changed.
+#[derive(Default)]
+pub struct RoamTracker {
+  current: HashMap<MacAddress, CurrentAccessPoint>,
+  pending: HashMap<MacAddress, PendingAssociation>,
+  counts: HashMap<MacAddress, u64>,
+}
+
+impl RoamTracker {
+  pub fn counts(&self) -> impl Iterator<Item = (&MacAddress, &u64)> {
+    self.counts.iter()
+  }
+
+  pub fn count(&self, sta: MacAddress) -> u64 {
+    self.counts.get(&sta).copied().unwrap_or(0)
+  }
+
+  /// `sta` asking `bssid` to (re)associate, only a move once accepted.
+  pub fn association_request(
+    &mut self,
+    now: Instant,
+    sta: MacAddress,
+    bssid: MacAddress,
+    ssid: Option<&[u8]>,
+  ) {
+    self.pending.insert(
+      sta,
+      PendingAssociation {
+        bssid,
+        ssid: ssid.map(<[u8]>::to_vec),
+        time: now,
+      },
+    );
+  }
+
+  /// `bssid` answering `sta`, where it is from now on if `accepted`.
+  /// `ssid` is used when the request didn't have one.
+  pub fn association_response(
+    &mut self,
+    now: Instant,
+    sta: MacAddress,
+    bssid: MacAddress,
+    accepted: bool,
+    ssid: &[u8],
+  ) -> Option<Roam> {
+    let pending = self.take_pending(now, sta, bssid);
+    if !accepted {
+      return None;
+    }
+
+    let pending_ssid = pending.and_then(|pending| pending.ssid);
+    self.moved(now, sta, bssid, pending_ssid.as_deref().unwrap_or(ssid))
+  }
+
+  /// Data between `sta` and `bssid`. Only a roam right after asking to
+  /// associate, or between access points of the same SSID.
+  pub fn data(
+    &mut self,
+    now: Instant,
+    sta: MacAddress,
+    bssid: MacAddress,
+    ssid: &[u8],
+  ) -> Option<Roam> {
+    if let Some(current) = self.current.get(&sta) {
+      if current.bssid == bssid {
+        return None;
+      }
+    }
+
+    // the response was missed
+    if let Some(pending) = self.take_pending(now, sta, bssid) {
+      let ssid = pending.ssid.unwrap_or_else(|| ssid.to_vec());
+      return self.moved(now, sta, bssid, &ssid);
+    }
+
+    if let Some(current) = self.current.get(&sta) {
+      if current.ssid != ssid || now.duration_since(current.since) < DATA_ROAM_HOLDOFF {
+        return None;
+      }
+    }
+
+    self.moved(now, sta, bssid, ssid)
+  }
+
+  /// The request from `sta` to `bssid`, if it's recent.
+  fn take_pending(
+    &mut self,
+    now: Instant,
+    sta: MacAddress,
+    bssid: MacAddress,
+  ) -> Option<PendingAssociation> {
+    let pending = self.pending.get(&sta)?;
+    if pending.bssid != bssid {
+      return None;
+    }
+
+    self
+      .pending
+      .remove(&sta)
+      .filter(|pending| now.duration_since(pending.time) < PENDING_TIMEOUT)
+  }
+
+  fn moved(
+    &mut self,
+    now: Instant,
+    sta: MacAddress,
+    bssid: MacAddress,
+    ssid: &[u8],
+  ) -> Option<Roam> {
+    let next = CurrentAccessPoint {
+      bssid,
+      ssid: ssid.to_vec(),
+      since: now,
+    };
+
+    let Some(current) = self.current.get_mut(&sta) else {
+      self.current.insert(sta, next);
+      return None;
+    };
+    if current.bssid == bssid {
+      return None;
+    }
+
+    let roam = Roam {
+      sta,
+      from_bssid: current.bssid,
+      to_bssid: bssid,
+      ssid: next.ssid.clone(),
+      duration_on_prev: now.duration_since(current.since).as_secs_f64(),
+    };
+    *current = next;
+    *self.counts.entry(sta).or_default() += 1;
+
+    Some(roam)
+  }
+}
+
+#[test]
+fn test_roaming() {
+  use super::test_mac;
+
+  let sta = test_mac([0x00, 0x11, 0x22, 0, 0, 1]);
+  let ap1 = test_mac([0x00, 0x11, 0x22, 0, 0, 10]);
+  let ap2 = test_mac([0x00, 0x11, 0x22, 0, 0, 11]);
+  let ap3 = test_mac([0x00, 0x11, 0x22, 0, 0, 13]);
+  let guest = test_mac([0x00, 0x11, 0x22, 0, 0, 12]);
+  let start = Instant::now();
+  let later = |secs| start + Duration::from_secs(secs);
+
+  let mut tracker = RoamTracker::default();
+
+  tracker.association_request(start, sta, ap1, Some(&b"corp"[..]));
+  assert!(tracker
+    .association_response(start, sta, ap1, true, b"")
+    .is_none());
+  assert!(tracker.data(later(1), sta, ap1, b"corp").is_none());
+
+  // turned away, it stays where it is
+  tracker.association_request(later(20), sta, ap3, Some(&b"corp"[..]));
+  assert!(tracker
+    .association_response(later(20), sta, ap3, false, b"corp")
+    .is_none());
+
+  tracker.association_request(later(30), sta, ap2, Some(&b"corp"[..]));
+  let roam = tracker
+    .association_response(later(30), sta, ap2, true, b"")
+    .unwrap();
+  assert_eq!(roam.from_bssid, ap1);
+  assert_eq!(roam.to_bssid, ap2);
+  assert_eq!(roam.ssid, b"corp");
+  assert!((roam.duration_on_prev - 30.0).abs() < 0.001);
+
+  // frames the old access point still had buffered
+  assert!(tracker.data(later(31), sta, ap1, b"corp").is_none());
+  // data alone doesn't move it to another network
+  assert!(tracker.data(later(60), sta, guest, b"guest").is_none());
+
+  let roam = tracker.data(later(90), sta, ap1, b"corp").unwrap();
+  assert_eq!(roam.from_bssid, ap2);
+
+  // a missed response, but data with the new access point right after
+  tracker.association_request(later(100), sta, guest, None);
+  let roam = tracker.data(later(100), sta, guest, b"guest").unwrap();
+  assert_eq!(roam.ssid, b"guest");
+  assert_eq!(tracker.count(sta), 3);
+}
@@ -0,0 +1,286 @@
+use super::{
+  format_mac, is_locally_administered, parse_mac, AccessPointInfo, Alert, AlertKind, Security,
+  Severity,
+};
+use crate::error::{err_msg, Result};
+use ieee80211::MacAddress;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A network we expect to see, anything else using its SSID is suspicious.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct KnownNetwork {
+  pub ssid: String,
+  /// empty allows any
+  #[serde(default)]
+  pub bssids: Vec<String>,
+  #[serde(default)]
+  pub channels: Vec<u8>,
+  pub security: Option<Security>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RogueApConfig {
+  #[serde(default)]
+  pub known: Vec<KnownNetwork>,
+}
+
+impl RogueApConfig {
+  pub fn validate(&self) -> Result<()> {
+    RogueApDetector::new(self).map(|_| ())
+  }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RogueApReason {
+  /// a known SSID from a BSSID that isn't in the list
+  UnknownBssid,
+  /// a known SSID on a channel that isn't in the list
+  UnexpectedChannel,
+  /// different security than configured, or than other BSSIDs with the SSID
+  SecurityMismatch,
+  /// a BSSID that was encrypted is now open
+  SecurityDowngrade,
+  /// the same SSID from BSSIDs of different vendors
+  VendorMismatch,
+}
+
+impl RogueApReason {
+  fn severity(self) -> Severity {
+    match self {
+      // clients would connect without noticing
+      Self::UnknownBssid | Self::SecurityDowngrade => Severity::Critical,
+      Self::UnexpectedChannel | Self::SecurityMismatch | Self::VendorMismatch => Severity::Warning,
+    }
+  }
+}
+
+struct Known {
+  network: KnownNetwork,
+  bssids: HashSet<MacAddress>,
+}
+
+struct SeenAccessPoint {
+  ssid: Vec<u8>,
+  security: Option<Security>,
+}
+
+/// Evil twins and rogue APs, from beacons and probe responses.
+pub struct RogueApDetector {
+  known: Vec<Known>,
+  seen: HashMap<MacAddress, SeenAccessPoint>,
+  ssid_bssids: HashMap<Vec<u8>, HashSet<MacAddress>>,
+  // each reason only once per BSSID
+  alerted: HashSet<(MacAddress, RogueApReason)>,
+}
+
+fn oui(mac: MacAddress) -> Option<[u8; 3]> {
+  // often extra BSSIDs of the same radio
+  if is_locally_administered(mac) {
+    return None;
+  }
+
+  let bytes = mac.as_bytes();
+  Some([bytes[0], bytes[1], bytes[2]])
+}
+
+impl RogueApDetector {
+  pub fn new(config: &RogueApConfig) -> Result<Self> {
+    let mut known = Vec::new();
+    for network in &config.known {
+      let mut bssids = HashSet::new();
+      for bssid in &network.bssids {
+        let bssid = parse_mac(bssid)
+          .and_then(|bssid| MacAddress::from_bytes(&bssid).ok())
+          .ok_or_else(|| err_msg(format!("bad bssid {:?} for {:?}", bssid, network.ssid)))?;
+        bssids.insert(bssid);
+      }
+
+      known.push(Known {
+        network: network.clone(),
+        bssids,
+      });
+    }
+
+    Ok(Self {
+      known,
+      seen: HashMap::new(),
+      ssid_bssids: HashMap::new(),
+      alerted: HashSet::new(),
+    })
+  }
+
+  pub fn access_point(&mut self, bssid: MacAddress, info: &AccessPointInfo) -> Vec<Alert> {
+    // hidden networks all look the same
+    if info.ssid.iter().all(|byte| *byte == 0) {
+      return Vec::new();
+    }
+
+    let mut reasons = Vec::new();
+    // the twin gets the blame, not the access point we know
+    let mut allowed = false;
+
+    let ssid = String::from_utf8_lossy(&info.ssid);
+    if let Some(Known { network, bssids }) =
+      self.known.iter().find(|known| known.network.ssid == ssid)
+    {
+      allowed = bssids.contains(&bssid);
+      if !bssids.is_empty() && !allowed {
+        reasons.push(RogueApReason::UnknownBssid);
+      }
+
+      if let Some(channel) = info.channel {
+        if !network.channels.is_empty() && !network.channels.contains(&channel) {
+          reasons.push(RogueApReason::UnexpectedChannel);
+        }
+      }
+
+      if let (Some(expected), Some(security)) = (network.security, info.security) {
+        if security != expected {
+          reasons.push(if security.is_open() && !expected.is_open() {
+            RogueApReason::SecurityDowngrade
+          } else {
+            RogueApReason::SecurityMismatch
+          });
+        }
+      }
+    }
+
+    let last = self.seen.get(&bssid);
+    if let (Some(last), Some(security)) = (last, info.security) {
+      if last.ssid == info.ssid
+        && last.security.map_or(false, |last| !last.is_open())
+        && security.is_open()
+      {
+        reasons.push(RogueApReason::SecurityDowngrade);
+      }
+    }
+    let security_changed = last.map_or(false, |last| last.security != info.security);
+
+    let others = self.ssid_bssids.entry(info.ssid.clone()).or_default();
+    let is_new = others.insert(bssid);
+    // only when it's new to the SSID or changed, not every beacon
+    if !allowed && (is_new || security_changed) {
+      for other in others.iter().filter(|other| **other != bssid) {
+        let Some(other_seen) = self.seen.get(other) else {
+          continue;
+        };
+
+        if let (Some(other_security), Some(security)) = (other_seen.security, info.security) {
+          if other_security != security {
+            reasons.push(RogueApReason::SecurityMismatch);
+          }
+        }
+
+        if let (Some(other_oui), Some(oui)) = (oui(*other), oui(bssid)) {
+          if other_oui != oui {
+            reasons.push(RogueApReason::VendorMismatch);
+          }
+        }
+      }
+    }
+
+    self.seen.insert(
+      bssid,
+      SeenAccessPoint {
+        ssid: info.ssid.clone(),
+        security: info.security,
+      },
+    );
+
+    // several checks and other BSSIDs can give the same reason
+    let mut unique = HashSet::new();
+    reasons.retain(|reason| unique.insert(*reason));
+
+    reasons
+      .into_iter()
+      .filter(|reason| self.alerted.insert((bssid, *reason)))
+      .map(|reason| {
+        Alert::new(
+          reason.severity(),
+          AlertKind::RogueAccessPoint {
+            bssid,
+            ssid: ssid.to_string(),
+            reason,
+          },
+          format!(
+            "possible rogue access point {} for {:?}: {:?}",
+            format_mac(bssid),
+            ssid,
+            reason
+          ),
+        )
+      })
+      .collect()
+  }
+}
+
+#[test]
+fn test_rogue_ap() {
+  let known = MacAddress::from_bytes(&[0x00, 0x11, 0x22, 0, 0, 1]).unwrap();
+  let twin = MacAddress::from_bytes(&[0x00, 0x99, 0x88, 0, 0, 1]).unwrap();
+
+  let mut detector = RogueApDetector::new(&RogueApConfig {
+    known: vec![KnownNetwork {
+      ssid: "corp".to_string(),
+      bssids: vec!["00:11:22:00:00:01".to_string()],
+      channels: vec![6],
+      security: Some(Security::Wpa2),
+    }],
+  })
+  .unwrap();
+
+  let info = |channel, security| AccessPointInfo {
+    ssid: b"corp".to_vec(),
+    channel: Some(channel),
+    security: Some(security),
+  };
+  let reasons = |alerts: Vec<Alert>| -> Vec<RogueApReason> {
+    alerts
+      .into_iter()
+      .filter_map(|alert| match alert.kind {
+        AlertKind::RogueAccessPoint { reason, .. } => Some(reason),
+        _ => None,
+      })
+      .collect()
+  };
+
+  assert!(detector
+    .access_point(known, &info(6, Security::Wpa2))
+    .is_empty());
+
+  assert_eq!(
+    reasons(detector.access_point(twin, &info(11, Security::Open))),
+    vec![
+      RogueApReason::UnknownBssid,
+      RogueApReason::UnexpectedChannel,
+      RogueApReason::SecurityDowngrade,
+      RogueApReason::SecurityMismatch,
+      RogueApReason::VendorMismatch,
+    ]
+  );
+
+  // known BSSID dropping to open, not a mismatch with the twin
+  assert_eq!(
+    reasons(detector.access_point(known, &info(6, Security::Open))),
+    vec![RogueApReason::SecurityDowngrade]
+  );
+
+  // only once
+  assert!(detector
+    .access_point(twin, &info(11, Security::Open))
+    .is_empty());
+
+  // mismatching both others still alerts each reason once
+  let third = MacAddress::from_bytes(&[0x00, 0x77, 0x66, 0, 0, 1]).unwrap();
+  assert_eq!(
+    reasons(detector.access_point(third, &info(6, Security::Wpa))),
+    vec![
+      RogueApReason::UnknownBssid,
+      RogueApReason::SecurityMismatch,
+      RogueApReason::VendorMismatch,
+    ]
+  );
+}
@@ -0,0 +1,209 @@
+use serde::Serialize;
+use std::{
+  collections::VecDeque,
+  time::{Duration, Instant},
+};
+
+pub const SIGNAL_STATS_WINDOWS: [Duration; 3] = [
+  Duration::from_secs(10),
+  Duration::from_secs(60),
+  Duration::from_secs(10 * 60),
+];
+
+const BUCKET_DURATION: Duration = Duration::from_secs(1);
+const MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SignalStats {
+  pub window_secs: u64,
+  pub count: u32,
+  pub min: i8,
+  pub max: i8,
+  pub mean: f64,
+  pub stddev: f64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SignalHistoryReport {
+  pub vendor: Option<&'static str>,
+  pub stats: Vec<SignalStats>,
+  /// mean signal of every second in the window, oldest first
+  pub sparkline: Vec<Option<f64>>,
+}
+
+struct Bucket {
+  start: Instant,
+  count: u32,
+  sum: f64,
+  sum_squares: f64,
+  min: i8,
+  max: i8,
+}
+
+impl Bucket {
+  fn new(start: Instant, signal: i8) -> Self {
+    let value = f64::from(signal);
+
+    Self {
+      start,
+      count: 1,
+      sum: value,
+      sum_squares: value * value,
+      min: signal,
+      max: signal,
+    }
+  }
+
+  fn add(&mut self, signal: i8) {
+    let value = f64::from(signal);
+
+    self.count += 1;
+    self.sum += value;
+    self.sum_squares += value * value;
+    self.min = self.min.min(signal);
+    self.max = self.max.max(signal);
+  }
+
+  fn mean(&self) -> f64 {
+    self.sum / f64::from(self.count)
+  }
+}
+
+/// Signal samples of one transmitter, kept as one bucket per second so busy
+/// devices don't use more memory.
+#[derive(Default)]
+pub struct SignalHistory {
+  buckets: VecDeque<Bucket>,
+}
+
+impl SignalHistory {
+  pub fn push(&mut self, now: Instant, signal: i8) {
+    if let Some(bucket) = self
+      .buckets
+      .back_mut()
+      .filter(|bucket| now.duration_since(bucket.start) < BUCKET_DURATION)
+    {
+      bucket.add(signal);
+    } else {
+      self.buckets.push_back(Bucket::new(now, signal));
+    }
+
+    while let Some(bucket) = self.buckets.front() {
+      if now.duration_since(bucket.start) < MAX_AGE {
+        break;
+      }
+      self.buckets.pop_front();
+    }
+  }
+
+  fn buckets_in(&self, now: Instant, window: Duration) -> impl Iterator<Item = &Bucket> {
+    self
+      .buckets
+      .iter()
+      .filter(move |bucket| now.duration_since(bucket.start) < window)
+  }
+
+  pub fn stats(&self, now: Instant, window: Duration) -> Option<SignalStats> {
+    let mut buckets = self.buckets_in(now, window);
+
+    let first = buckets.next()?;
+    let mut count = first.count;
+    let mut sum = first.sum;
+    let mut sum_squares = first.sum_squares;
+    let mut min = first.min;
+    let mut max = first.max;
+
+    for bucket in buckets {
+      count += bucket.count;
+      sum += bucket.sum;
+      sum_squares += bucket.sum_squares;
+      min = min.min(bucket.min);
+      max = max.max(bucket.max);
+    }
+
+    let mean = sum / f64::from(count);
+    let variance = (sum_squares / f64::from(count) - mean * mean).max(0.0);
+
+    Some(SignalStats {
+      window_secs: window.as_secs(),
+      count,
+      min,
+      max,
+      mean,
+      stddev: variance.sqrt(),
+    })
+  }
+
+  pub fn all_stats(&self, now: Instant) -> Vec<SignalStats> {
+    SIGNAL_STATS_WINDOWS
+      .iter()
+      .filter_map(|window| self.stats(now, *window))
+      .collect()
+  }
+
+  pub fn sparkline(&self, now: Instant, window: Duration) -> Vec<Option<f64>> {
+    #[allow(clippy::cast_possible_truncation)]
+    let seconds = window.as_secs().min(MAX_AGE.as_secs()) as usize;
+    let mut sparkline = vec![None; seconds];
+
+    for bucket in self.buckets_in(now, window) {
+      #[allow(clippy::cast_possible_truncation)]
+      let age = now.duration_since(bucket.start).as_secs() as usize;
+      if age < seconds {
+        sparkline[seconds - 1 - age] = Some(bucket.mean());
+      }
+    }
+
+    sparkline
+  }
+
+  pub fn report(&self, now: Instant, window: Duration) -> SignalHistoryReport {
+    SignalHistoryReport {
+      vendor: None,
+      stats: self.all_stats(now),
+      sparkline: self.sparkline(now, window),
+    }
+  }
+}
+
+#[test]
+fn test_signal_stats() {
+  let start = Instant::now();
+  let mut history = SignalHistory::default();
+
+  history.push(start, -40);
+  history.push(start, -60);
+  history.push(start + Duration::from_secs(30), -50);
+
+  let now = start + Duration::from_secs(30);
+
+  let last_10s = history.stats(now, Duration::from_secs(10)).unwrap();
+  assert_eq!(last_10s.count, 1);
+  assert!((last_10s.mean - -50.0).abs() < 1e-9);
+  assert!(last_10s.stddev.abs() < 1e-9);
+
+  let last_minute = history.stats(now, Duration::from_secs(60)).unwrap();
+  assert_eq!(last_minute.count, 3);
+  assert_eq!((last_minute.min, last_minute.max), (-60, -40));
+  assert!((last_minute.mean - -50.0).abs() < 1e-9);
+  assert!((last_minute.stddev - (200.0_f64 / 3.0).sqrt()).abs() < 1e-9);
+
+  let sparkline = history.sparkline(now, Duration::from_secs(60));
+  assert_eq!(sparkline.len(), 60);
+  assert_eq!(sparkline[59], Some(-50.0));
+  assert_eq!(sparkline[29], Some(-50.0));
+  assert_eq!(sparkline[28], None);
+}
+
+#[test]
+fn test_signal_history_expires() {
+  let start = Instant::now();
+  let mut history = SignalHistory::default();
+
+  history.push(start, -40);
+  history.push(start + MAX_AGE, -70);
+
+  let stats = history.stats(start + MAX_AGE, MAX_AGE * 2).unwrap();
+  assert_eq!(stats.count, 1);
+  assert_eq!(stats.min, -70);
+}
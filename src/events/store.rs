@@ -1,15 +1,31 @@
-use super::{hash_macs, is_broadcast};
-use ieee80211::MacAddress;
+use super::{
+  is_broadcast, is_locally_administered, ordered_pair, vendor, Alert, AlertLimiter,
+  BeaconFloodDetector, ChannelUtilization, DeauthFloodDetector, DeviceFingerprint, DistanceConfig,
+  FrameDeduplicator, KarmaDetector, LinkTraffic, PhyInfo, Position, PositioningConfig,
+  RandomMacCorrelator, Roam, RoamTracker, RogueApDetector, Security, Severity, SignalHistory,
+  SignalHistoryReport, SignalStats, SignatureDatabase, Throughput, Watchlist,
+};
+use crate::{
+  alert_sinks::AlertSinks,
+  config::Config,
+  packet_capture::{AntennaSignal, CaptureStats},
+};
+use ieee80211::{FrameLayer, MacAddress};
+use log::{info, warn};
 use serde::Serialize;
 use std::{
-  collections::{HashMap, HashSet},
+  collections::{BTreeMap, HashMap, HashSet, VecDeque},
+  mem,
+  sync::Arc,
   time::{Duration, Instant},
 };
 
+const MAX_RECENT_ALERTS: usize = 100;
+
 #[derive(Serialize, Debug)]
-#[serde(tag = "type", content = "data")] // {type: "NewAddress", data: "aa:aa:aa"}
+#[serde(tag = "type", content = "data")] // {type: "NewAddress", data: ["aa:aa:aa", "Apple"]}
 pub enum Event {
-  NewAddress(MacAddress),
+  NewAddress(MacAddress, Option<&'static str>), // vendor
 
   AccessPoint(MacAddress, AccessPointInfo),
 
@@ -17,14 +33,74 @@ pub enum Event {
 
   ProbeRequest(MacAddress, Vec<u8>), // from, ssid
 
+  DeviceFingerprint(MacAddress, DeviceFingerprint),
+
+  // a station moving to another access point, and how often it has
+  Roam(Roam),
+  RoamCount(MacAddress, u64),
+
+  // a random address, see `is_locally_administered`
+  Randomized(MacAddress),
+  // random addresses that are probably one device
+  SameDevice(Vec<MacAddress>),
+
+  // on the watchlist: label, present
+  Presence(MacAddress, String, bool),
+
   // Loss(MacAddress, u64, u64), // addr, # lost, # received
-  Signal(MacAddress, i8),
+  // addr, combined signal, per antenna signals
+  Signal(MacAddress, i8, Vec<AntennaSignal>),
+
+  // one for each of SIGNAL_STATS_WINDOWS that has samples
+  SignalStats(MacAddress, Vec<SignalStats>),
+
+  // addr, sensors that heard it recently
+  SensorSignals(MacAddress, Vec<SensorSignal>),
+
+  // estimated from SensorSignals
+  Position(MacAddress, Position),
+
+  // meters, estimated from Signal
+  Distance(MacAddress, f64),
 
   Rate(MacAddress, u64),
+  // % of the frames counted in Rate that were retries
+  RetryRate(MacAddress, f64),
+
+  // transmitter, receiver
+  Throughput(MacAddress, MacAddress, Throughput),
+
+  // MHz, since the last one
+  ChannelUtilization(u16, ChannelUtilization),
+  // addr, % of the last interval spent transmitting
+  Airtime(MacAddress, f64),
+
+  Phy(MacAddress, PhyInfo),
 
   // #received, #correct
   BeaconQuality(MacAddress, u64, u64),
 
+  // frames with a bad FCS, per transmitter
+  BadFrames(MacAddress, u64),
+  // #bad, #total
+  TotalBadFrames(u64, u64),
+
+  // frames per subtype name, per transmitter and overall
+  FrameStats(MacAddress, BTreeMap<&'static str, u64>),
+  TotalFrameStats(BTreeMap<&'static str, u64>),
+
+  CaptureStats(CaptureStats),
+
+  Alert(Alert),
+  // collapsed into one node
+  FakeAccessPoints(Vec<MacAddress>),
+  // thought to be fake, but kept beaconing normally
+  RealAccessPoint(MacAddress),
+
+  // name, connected
+  Sensor(String, bool),
+  SensorStats(String, CaptureStats),
+
   Error(String),
 }
 
@@ -32,6 +108,13 @@ pub enum Event {
 pub struct AccessPointInfo {
   pub ssid: Vec<u8>,
   pub channel: Option<u8>,
+  pub security: Option<Security>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SensorSignal {
+  pub sensor: String,
+  pub signal: i8,
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -47,8 +130,8 @@ pub struct Store {
 
   addresses: HashMap<MacAddress, Instant>,
 
-  // TODO instead of String use (mac1, mac2) in hashed sorted
-  connections: HashMap<String, ConnectionType>,
+  // (mac1, mac2) sorted by ordered_pair
+  connections: HashMap<(MacAddress, MacAddress), ConnectionType>,
   access_points: HashMap<MacAddress, AccessPointInfo>,
   probes: HashMap<MacAddress, HashSet<Vec<u8>>>,
   frame_count: HashMap<MacAddress, u64>,
@@ -58,21 +141,90 @@ pub struct Store {
   next_signal_event_update: HashMap<MacAddress, Instant>,
   next_rate_event_update: HashMap<MacAddress, Instant>,
   rate_last_frame_count: HashMap<MacAddress, u64>,
+  retry_count: HashMap<MacAddress, u64>,
+  rate_last_retry_count: HashMap<MacAddress, u64>,
 
   // interval, first beacon time
   beacon_quality_intervals: HashMap<MacAddress, (f64, Instant)>,
   next_beacon_quality_update: HashMap<MacAddress, Instant>,
 
-  signals: HashMap<MacAddress, (i8, Instant)>,
+  signals: HashMap<MacAddress, (i8, Vec<AntennaSignal>, Instant)>,
+  signal_histories: HashMap<MacAddress, SignalHistory>,
+  // transmitter -> sensor -> last signal
+  sensor_signals: HashMap<MacAddress, HashMap<String, (i8, Instant)>>,
+  positions: HashMap<MacAddress, Position>,
+
+  // (transmitter, receiver): since the last Throughput, total, last one wasn't empty
+  link_traffic: HashMap<(MacAddress, MacAddress), (LinkTraffic, LinkTraffic, bool)>,
+  next_throughput_update: Instant,
+
+  // since the last airtime events, by frequency: airtime, #frames
+  channel_airtime: HashMap<u16, (Duration, u64)>,
+  transmitter_airtime: HashMap<MacAddress, Duration>,
+  last_airtime_update: Instant,
+
+  // info, changed since last event
+  phys: HashMap<MacAddress, (PhyInfo, bool)>,
+  next_phy_event_update: HashMap<MacAddress, Instant>,
+
+  total_frame_count: u64,
+  total_bad_frame_count: u64,
+  bad_frame_count: HashMap<MacAddress, u64>,
+  changed_bad_frame_counts: HashSet<MacAddress>,
+  total_bad_frame_count_changed: bool,
+  next_bad_frames_update: Instant,
+
+  // by subtype name
+  frame_subtype_counts: BTreeMap<&'static str, u64>,
+  transmitter_frame_subtype_counts: HashMap<MacAddress, BTreeMap<&'static str, u64>>,
+  changed_frame_stats: HashSet<MacAddress>,
+  frame_stats_changed: bool,
+  next_frame_stats_update: Instant,
+
+  last_capture_stats: Option<CaptureStats>,
+  last_error: Option<String>,
+
+  // name -> connected, last stats
+  sensors: HashMap<String, (bool, Option<CaptureStats>)>,
+
+  deauth_flood: DeauthFloodDetector,
+  beacon_flood: BeaconFloodDetector,
+  rogue_ap: RogueApDetector,
+  karma: KarmaDetector,
+  random_macs: RandomMacCorrelator,
+  // frames heard by several sensors
+  dedup: FrameDeduplicator,
+  roaming: RoamTracker,
+  signatures: SignatureDatabase,
+  device_fingerprints: HashMap<MacAddress, DeviceFingerprint>,
+
+  watchlist: Watchlist,
+  watchlist_timeout: Duration,
+  // label, present
+  watched: HashMap<MacAddress, (String, bool)>,
+
+  alert_limiter: AlertLimiter,
+  alerts_to_websocket: bool,
+  alert_sinks: Option<AlertSinks>,
+  // for clients that connect late, oldest first
+  recent_alerts: VecDeque<Alert>,
+
+  distance_config: Option<DistanceConfig>,
+  positioning_config: Option<PositioningConfig>,
 
   signal_event_update_interval: Duration,
   signal_event_timeout: Duration,
   rate_event_update_interval: Duration,
   beacon_quality_update_interval: Duration,
+  bad_frames_update_interval: Duration,
+  frame_stats_update_interval: Duration,
+  phy_event_update_interval: Duration,
+  airtime_update_interval: Duration,
+  throughput_update_interval: Duration,
 }
 
 impl Store {
-  pub fn new() -> Self {
+  pub fn new(config: &Config) -> Self {
     Self {
       buffer: Vec::new(),
       addresses: HashMap::new(),
@@ -84,14 +236,66 @@ impl Store {
       next_signal_event_update: HashMap::new(),
       next_rate_event_update: HashMap::new(),
       rate_last_frame_count: HashMap::new(),
+      retry_count: HashMap::new(),
+      rate_last_retry_count: HashMap::new(),
       beacon_quality_intervals: HashMap::new(),
       next_beacon_quality_update: HashMap::new(),
       signals: HashMap::new(),
+      signal_histories: HashMap::new(),
+      sensor_signals: HashMap::new(),
+      positions: HashMap::new(),
+      link_traffic: HashMap::new(),
+      next_throughput_update: Instant::now(),
+      channel_airtime: HashMap::new(),
+      transmitter_airtime: HashMap::new(),
+      last_airtime_update: Instant::now(),
+      phys: HashMap::new(),
+      next_phy_event_update: HashMap::new(),
+      total_frame_count: 0,
+      total_bad_frame_count: 0,
+      bad_frame_count: HashMap::new(),
+      changed_bad_frame_counts: HashSet::new(),
+      total_bad_frame_count_changed: false,
+      next_bad_frames_update: Instant::now(),
+      frame_subtype_counts: BTreeMap::new(),
+      transmitter_frame_subtype_counts: HashMap::new(),
+      changed_frame_stats: HashSet::new(),
+      frame_stats_changed: false,
+      next_frame_stats_update: Instant::now(),
+      last_capture_stats: None,
+      last_error: None,
+
+      sensors: HashMap::new(),
+
+      deauth_flood: DeauthFloodDetector::new(config.deauth_flood.clone()),
+      beacon_flood: BeaconFloodDetector::new(config.beacon_flood.clone()),
+      rogue_ap: RogueApDetector::new(&config.rogue_ap).expect("validated in Config::load"),
+      karma: KarmaDetector::new(config.karma.clone()),
+      random_macs: RandomMacCorrelator::default(),
+      dedup: FrameDeduplicator::default(),
+      roaming: RoamTracker::default(),
+      signatures: SignatureDatabase::bundled(),
+      device_fingerprints: HashMap::new(),
+      watchlist: Watchlist::new(&config.watchlist).expect("validated in Config::load"),
+      watchlist_timeout: Duration::from_secs(config.watchlist.timeout_secs),
+      watched: HashMap::new(),
+      alert_limiter: AlertLimiter::new(&config.alerts),
+      alerts_to_websocket: config.alerts.websocket,
+      alert_sinks: None,
+      recent_alerts: VecDeque::new(),
+
+      distance_config: config.distance.clone(),
+      positioning_config: config.positioning.clone(),
 
       signal_event_update_interval: Duration::from_secs(1),
       signal_event_timeout: Duration::from_secs(5),
       rate_event_update_interval: Duration::from_secs(1),
       beacon_quality_update_interval: Duration::from_secs(1),
+      bad_frames_update_interval: Duration::from_secs(1),
+      frame_stats_update_interval: Duration::from_secs(1),
+      phy_event_update_interval: Duration::from_secs(1),
+      airtime_update_interval: Duration::from_secs(1),
+      throughput_update_interval: Duration::from_secs(1),
     }
   }
 
@@ -99,15 +303,232 @@ impl Store {
     self.buffer.drain(..).collect()
   }
 
+  /// events that rebuild the current state for a client that connects late
+  pub fn snapshot(&self) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    for mac in self.addresses.keys() {
+      events.push(Event::NewAddress(*mac, vendor(mac.as_bytes())));
+      if is_locally_administered(*mac) && !is_broadcast(*mac) {
+        events.push(Event::Randomized(*mac));
+      }
+    }
+
+    for (mac, fingerprint) in &self.device_fingerprints {
+      events.push(Event::DeviceFingerprint(*mac, fingerprint.clone()));
+    }
+
+    for group in self.random_macs.groups() {
+      events.push(Event::SameDevice(group.clone()));
+    }
+
+    for (mac, count) in self.roaming.counts() {
+      events.push(Event::RoamCount(*mac, *count));
+    }
+
+    for (mac, (label, present)) in &self.watched {
+      events.push(Event::Presence(*mac, label.clone(), *present));
+    }
+
+    for (mac, info) in &self.access_points {
+      events.push(Event::AccessPoint(*mac, info.clone()));
+    }
+
+    let fake_access_points = self.beacon_flood.hidden();
+    if !fake_access_points.is_empty() {
+      events.push(Event::FakeAccessPoints(fake_access_points));
+    }
+
+    for ((mac1, mac2), kind) in &self.connections {
+      events.push(Event::Connection(*mac1, *mac2, kind.clone()));
+    }
+
+    for (mac, ssid_list) in &self.probes {
+      for ssid in ssid_list {
+        events.push(Event::ProbeRequest(*mac, ssid.clone()));
+      }
+    }
+
+    // rates and beacon qualities are sent again by their timers
+    for transmitter_address in self.next_signal_event_update.keys() {
+      if let Some((signal, antenna_signals, _time)) = self.signals.get(transmitter_address) {
+        events.push(Event::Signal(
+          *transmitter_address,
+          *signal,
+          antenna_signals.clone(),
+        ));
+      }
+
+      if let Some(readings) = self.sensor_signals.get(transmitter_address) {
+        events.push(Event::SensorSignals(
+          *transmitter_address,
+          fresh_sensor_signals(readings, Instant::now(), self.signal_event_timeout),
+        ));
+      }
+
+      if let Some(position) = self.positions.get(transmitter_address) {
+        events.push(Event::Position(*transmitter_address, position.clone()));
+      }
+    }
+
+    for ((transmitter_address, receiver_address), (_recent, total, _sent)) in &self.link_traffic {
+      events.push(Event::Throughput(
+        *transmitter_address,
+        *receiver_address,
+        Throughput::new(LinkTraffic::default(), total.clone()),
+      ));
+    }
+
+    for (mac, (info, _changed)) in &self.phys {
+      events.push(Event::Phy(*mac, info.clone()));
+    }
+
+    for (mac, count) in &self.bad_frame_count {
+      events.push(Event::BadFrames(*mac, *count));
+    }
+    if self.total_bad_frame_count > 0 {
+      events.push(Event::TotalBadFrames(
+        self.total_bad_frame_count,
+        self.total_frame_count,
+      ));
+    }
+
+    for (mac, counts) in &self.transmitter_frame_subtype_counts {
+      events.push(Event::FrameStats(*mac, counts.clone()));
+    }
+    if !self.frame_subtype_counts.is_empty() {
+      events.push(Event::TotalFrameStats(self.frame_subtype_counts.clone()));
+    }
+
+    if let Some(stats) = &self.last_capture_stats {
+      events.push(Event::CaptureStats(stats.clone()));
+    }
+
+    for (name, (connected, stats)) in &self.sensors {
+      events.push(Event::Sensor(name.clone(), *connected));
+      if let Some(stats) = stats {
+        events.push(Event::SensorStats(name.clone(), stats.clone()));
+      }
+    }
+
+    for alert in &self.recent_alerts {
+      events.push(Event::Alert(alert.clone()));
+    }
+
+    if let Some(error) = &self.last_error {
+      events.push(Event::Error(error.clone()));
+    }
+
+    events
+  }
+
+  /// `mac` as bytes since it usually comes from a user
+  pub fn signal_history(&self, mac: [u8; 6], window: Duration) -> Option<SignalHistoryReport> {
+    let history = self
+      .signal_histories
+      .get(&MacAddress::from_bytes(&mac).ok()?)?;
+
+    let mut report = history.report(Instant::now(), window);
+    report.vendor = vendor(&mac);
+    Some(report)
+  }
+
+  pub fn total_frame_stats(&self) -> BTreeMap<&'static str, u64> {
+    self.frame_subtype_counts.clone()
+  }
+
+  pub fn frame_stats(&self, mac: [u8; 6]) -> Option<BTreeMap<&'static str, u64>> {
+    self
+      .transmitter_frame_subtype_counts
+      .get(&MacAddress::from_bytes(&mac).ok()?)
+      .cloned()
+  }
+
+  pub fn error(&mut self, message: String) {
+    self.last_error = Some(message.clone());
+    self.buffer.push(Event::Error(message));
+  }
+
   pub fn add_address(&mut self, mac: MacAddress) {
     let now = Instant::now();
 
     if self.addresses.insert(mac, now).is_none() {
-      self.buffer.push(Event::NewAddress(mac));
+      self
+        .buffer
+        .push(Event::NewAddress(mac, vendor(mac.as_bytes())));
+      if is_locally_administered(mac) && !is_broadcast(mac) {
+        self.buffer.push(Event::Randomized(mac));
+      }
+    }
+
+    if let Some((label, present)) = self.watched.get_mut(&mac) {
+      if !*present {
+        *present = true;
+        self.buffer.push(Event::Presence(mac, label.clone(), true));
+      }
+    } else if let Some(label) = self.watchlist.mac_label(mac) {
+      let label = label.to_string();
+      self.watch(mac, label);
+    }
+  }
+
+  fn watch(&mut self, mac: MacAddress, label: String) {
+    self.watched.insert(mac, (label.clone(), true));
+    self.buffer.push(Event::Presence(mac, label, true));
+  }
+
+  /// `timestamp` from the capture.
+  pub fn beacon(
+    &mut self,
+    mac: MacAddress,
+    ssid: &[u8],
+    sequence_number: Option<u16>,
+    signal: Option<i8>,
+    timestamp: Duration,
+  ) {
+    self.beacon_flood.expire(timestamp);
+
+    if self.beacon_flood.is_fake(mac) {
+      if self
+        .beacon_flood
+        .fake_beacon(timestamp, mac, sequence_number)
+      {
+        self.buffer.push(Event::RealAccessPoint(mac));
+      }
+      return;
+    }
+
+    if self.access_points.contains_key(&mac) {
+      return;
+    }
+
+    if let Some(alert) =
+      self
+        .beacon_flood
+        .new_access_point(timestamp, mac, ssid, sequence_number, signal)
+    {
+      self.alert(alert);
     }
   }
 
   pub fn access_point(&mut self, mac: MacAddress, info: AccessPointInfo) {
+    if self.beacon_flood.is_hidden(mac) {
+      return;
+    }
+
+    if !self.watched.contains_key(&mac) {
+      if let Some(label) = self.watchlist.ssid_label(&info.ssid) {
+        let label = label.to_string();
+        self.watch(mac, label);
+      }
+    }
+
+    // every beacon, so changes are noticed, other BSSIDs are only compared
+    // when something changed
+    for alert in self.rogue_ap.access_point(mac, &info) {
+      self.alert(alert);
+    }
+
     if self.access_points.contains_key(&mac) {
       // TODO changing ssid/channel?
       return;
@@ -117,6 +538,25 @@ impl Store {
     self.buffer.push(Event::AccessPoint(mac, info));
   }
 
+  pub fn probe_response(
+    &mut self,
+    transmitter_address: MacAddress,
+    receiver_address: MacAddress,
+    info: AccessPointInfo,
+  ) {
+    let probed = self
+      .probes
+      .get(&receiver_address)
+      .map_or(false, |ssid_list| ssid_list.contains(&info.ssid));
+    if probed {
+      if let Some(alert) = self.karma.probe_response(transmitter_address, &info.ssid) {
+        self.alert(alert);
+      }
+    }
+
+    self.access_point(transmitter_address, info);
+  }
+
   pub fn change_connection(
     &mut self,
     transmitter_address: MacAddress,
@@ -127,9 +567,9 @@ impl Store {
       return;
     }
 
-    let hash = hash_macs(transmitter_address, receiver_address);
+    let pair = ordered_pair(transmitter_address, receiver_address);
 
-    if let Some(old_kind) = self.connections.get(&hash) {
+    if let Some(old_kind) = self.connections.get(&pair) {
       if kind == *old_kind {
         return;
       }
@@ -149,7 +589,7 @@ impl Store {
       }
     }
 
-    self.connections.insert(hash, kind.clone());
+    self.connections.insert(pair, kind.clone());
     self.buffer.push(Event::Connection(
       transmitter_address,
       receiver_address,
@@ -172,6 +612,96 @@ impl Store {
     }
   }
 
+  /// From the elements of a probe or (re)association request.
+  pub fn device_fingerprint(&mut self, mac: MacAddress, elements: &[(u8, &[u8])]) {
+    let fingerprint = self.signatures.identify(elements);
+
+    // keep the first one, unless a later request is recognized
+    let replace = match self.device_fingerprints.get(&mac) {
+      None => true,
+      Some(last) => last.hash != fingerprint.hash && !last.is_known() && fingerprint.is_known(),
+    };
+    if replace {
+      self.device_fingerprints.insert(mac, fingerprint.clone());
+      self.buffer.push(Event::DeviceFingerprint(mac, fingerprint));
+    }
+  }
+
+  /// `sta` asking `bssid` to (re)associate, for `ssid` if the request had one.
+  pub fn association_request(&mut self, sta: MacAddress, bssid: MacAddress, ssid: Option<&[u8]>) {
+    if is_broadcast(bssid) {
+      return;
+    }
+
+    let ssid = ssid.filter(|ssid| !ssid.is_empty());
+    self
+      .roaming
+      .association_request(Instant::now(), sta, bssid, ssid);
+  }
+
+  /// `bssid` answering a (re)association request from `sta`.
+  pub fn association_response(&mut self, bssid: MacAddress, sta: MacAddress, accepted: bool) {
+    if is_broadcast(sta) {
+      return;
+    }
+
+    let ssid = self
+      .access_points
+      .get(&bssid)
+      .map_or(&[][..], |info| info.ssid.as_slice());
+    let roam = self
+      .roaming
+      .association_response(Instant::now(), sta, bssid, accepted, ssid);
+    self.roamed(roam);
+  }
+
+  /// Data between a station and an access point we've had beacons from.
+  pub fn station_data(&mut self, transmitter_address: MacAddress, receiver_address: MacAddress) {
+    let (sta, bssid) = match (
+      self.access_points.contains_key(&transmitter_address),
+      self.access_points.contains_key(&receiver_address),
+    ) {
+      (false, true) => (transmitter_address, receiver_address),
+      (true, false) => (receiver_address, transmitter_address),
+      // between access points, or no access point at all
+      _ => return,
+    };
+    if is_broadcast(sta) {
+      return;
+    }
+
+    let roam = self
+      .roaming
+      .data(Instant::now(), sta, bssid, &self.access_points[&bssid].ssid);
+    self.roamed(roam);
+  }
+
+  fn roamed(&mut self, roam: Option<Roam>) {
+    if let Some(roam) = roam {
+      let sta = roam.sta;
+      self.buffer.push(Event::Roam(roam));
+      self
+        .buffer
+        .push(Event::RoamCount(sta, self.roaming.count(sta)));
+    }
+  }
+
+  pub fn random_probe_request(
+    &mut self,
+    mac: MacAddress,
+    fingerprint: u32,
+    sequence_number: Option<u16>,
+    ssid: Option<&[u8]>,
+  ) {
+    if let Some(group) =
+      self
+        .random_macs
+        .probe_request(Instant::now(), mac, fingerprint, sequence_number, ssid)
+    {
+      self.buffer.push(Event::SameDevice(group));
+    }
+  }
+
   pub fn update_beacon_quality(&mut self, transmitter_address: MacAddress, interval: f64) {
     self
       .beacon_count
@@ -201,10 +731,50 @@ impl Store {
     }
   }
 
-  pub fn update_signal(&mut self, transmitter_address: MacAddress, signal: i8) {
+  /// With a `sensor`, the signal used everywhere else is the strongest one
+  /// any sensor heard recently, with per-antenna signals only when it's this
+  /// sensor's.
+  pub fn update_signal(
+    &mut self,
+    transmitter_address: MacAddress,
+    sensor: Option<&str>,
+    signal: i8,
+    antenna_signals: Vec<AntennaSignal>,
+  ) {
     let now = Instant::now();
 
-    self.signals.insert(transmitter_address, (signal, now));
+    let (signal, antenna_signals) = if let Some(sensor) = sensor {
+      self.sensor_signal(transmitter_address, sensor, signal);
+
+      let strongest = fresh_sensor_signals(
+        &self.sensor_signals[&transmitter_address],
+        now,
+        self.signal_event_timeout,
+      )
+      .iter()
+      .map(|reading| reading.signal)
+      .max()
+      .unwrap_or(signal);
+
+      // another sensor's antennas would be a different receiver
+      if strongest == signal {
+        (signal, antenna_signals)
+      } else {
+        (strongest, Vec::new())
+      }
+    } else {
+      (signal, antenna_signals)
+    };
+
+    self
+      .signals
+      .insert(transmitter_address, (signal, antenna_signals.clone(), now));
+
+    self
+      .signal_histories
+      .entry(transmitter_address)
+      .or_default()
+      .push(now, signal);
 
     if !self
       .next_signal_event_update
@@ -215,17 +785,116 @@ impl Store {
         .next_signal_event_update
         .insert(transmitter_address, now + self.signal_event_update_interval);
 
-      self.buffer.push(Event::Signal(transmitter_address, signal));
+      self
+        .buffer
+        .push(Event::Signal(transmitter_address, signal, antenna_signals));
+      self.push_distance(transmitter_address, signal);
+    }
+  }
+
+  /// Only what `sensor` heard, sent with the next `SensorSignals`.
+  pub fn sensor_signal(&mut self, transmitter_address: MacAddress, sensor: &str, signal: i8) {
+    let now = Instant::now();
+
+    let readings = self.sensor_signals.entry(transmitter_address).or_default();
+    if let Some(reading) = readings.get_mut(sensor) {
+      *reading = (signal, now);
+    } else {
+      readings.insert(sensor.to_string(), (signal, now));
+    }
+  }
+
+  pub fn is_duplicate_frame(
+    &mut self,
+    sensor: &Arc<str>,
+    transmitter_address: Option<MacAddress>,
+    sequence_number: Option<u16>,
+    frame: &[u8],
+  ) -> bool {
+    self.dedup.is_duplicate(
+      Instant::now(),
+      sensor,
+      transmitter_address,
+      sequence_number,
+      frame,
+    )
+  }
+
+  pub fn update_phy(&mut self, transmitter_address: MacAddress, info: PhyInfo, is_data: bool) {
+    let info = match self.phys.get(&transmitter_address) {
+      Some((last_info, _changed)) if !is_data => info.with_rate_from(last_info),
+      _ => info,
+    };
+
+    if let Some((last_info, changed)) = self.phys.get_mut(&transmitter_address) {
+      if *last_info != info {
+        *last_info = info;
+        *changed = true;
+      }
+      return;
+    }
+
+    // first time, send right away and let the timer handle changes
+    self.phys.insert(transmitter_address, (info.clone(), false));
+    self.next_phy_event_update.insert(
+      transmitter_address,
+      Instant::now() + self.phy_event_update_interval,
+    );
+    self.buffer.push(Event::Phy(transmitter_address, info));
+  }
+
+  fn push_distance(&mut self, transmitter_address: MacAddress, signal: i8) {
+    if let Some(distance) = self.estimate_distance(transmitter_address, signal) {
+      self
+        .buffer
+        .push(Event::Distance(transmitter_address, distance));
+    }
+  }
+
+  fn estimate_distance(&self, transmitter_address: MacAddress, signal: i8) -> Option<f64> {
+    let distance_config = self.distance_config.as_ref()?;
+    let (phy_info, _changed) = self.phys.get(&transmitter_address)?;
+
+    distance_config.estimate_distance(phy_info.frequency?, signal)
+  }
+
+  fn push_position(&mut self, transmitter_address: MacAddress, signals: &[SensorSignal]) {
+    let Some(positioning_config) = &self.positioning_config else {
+      return;
+    };
+
+    let frequency = self
+      .phys
+      .get(&transmitter_address)
+      .and_then(|(phy_info, _changed)| phy_info.frequency);
+
+    let default_distance_config;
+    let distance_config = if let Some(distance_config) = &self.distance_config {
+      distance_config
+    } else {
+      default_distance_config = DistanceConfig::default();
+      &default_distance_config
+    };
+
+    if let Some(position) = positioning_config.estimate(signals, frequency, distance_config) {
+      self.positions.insert(transmitter_address, position.clone());
+      self
+        .buffer
+        .push(Event::Position(transmitter_address, position));
     }
   }
 
-  pub fn update_rate(&mut self, transmitter_address: MacAddress) {
+  pub fn update_rate(&mut self, transmitter_address: MacAddress, retry: bool) {
     self
       .frame_count
       .entry(transmitter_address)
       .and_modify(move |frame_count| *frame_count += 1)
       .or_insert(1);
 
+    if retry {
+      *self.retry_count.entry(transmitter_address).or_insert(0) += 1;
+    }
+
     if !self
       .next_rate_event_update
       .contains_key(&transmitter_address)
@@ -240,11 +909,207 @@ impl Store {
     }
   }
 
-  pub fn check_timers(&mut self) {
-    // TODO this is only called if packets are arriving!
+  pub fn add_traffic(
+    &mut self,
+    transmitter_address: MacAddress,
+    receiver_address: MacAddress,
+    layer: &FrameLayer,
+    length: usize,
+    retry: bool,
+  ) {
+    if is_broadcast(receiver_address) {
+      return;
+    }
+
+    let (recent, total, _sent) = self
+      .link_traffic
+      .entry((transmitter_address, receiver_address))
+      .or_default();
+    recent.add(layer, length, retry);
+    total.add(layer, length, retry);
+  }
+
+  pub fn add_airtime(
+    &mut self,
+    transmitter_address: Option<MacAddress>,
+    frequency: Option<u16>,
+    airtime: Duration,
+  ) {
+    if let Some(frequency) = frequency {
+      let (channel_airtime, frames) = self.channel_airtime.entry(frequency).or_default();
+      *channel_airtime += airtime;
+      *frames += 1;
+    }
 
+    if let Some(transmitter_address) = transmitter_address {
+      *self
+        .transmitter_airtime
+        .entry(transmitter_address)
+        .or_default() += airtime;
+    }
+  }
+
+  pub fn count_frame_subtype(
+    &mut self,
+    transmitter_address: Option<MacAddress>,
+    subtype: &'static str,
+  ) {
+    self.frame_stats_changed = true;
+
+    if let Some(transmitter_address) = transmitter_address {
+      *self
+        .transmitter_frame_subtype_counts
+        .entry(transmitter_address)
+        .or_default()
+        .entry(subtype)
+        .or_insert(0) += 1;
+      self.changed_frame_stats.insert(transmitter_address);
+    }
+
+    *self.frame_subtype_counts.entry(subtype).or_insert(0) += 1;
+  }
+
+  /// `timestamp` from the capture.
+  pub fn deauth(
+    &mut self,
+    transmitter_address: MacAddress,
+    receiver_address: MacAddress,
+    timestamp: Duration,
+  ) {
+    for alert in self
+      .deauth_flood
+      .frame(timestamp, transmitter_address, receiver_address)
+    {
+      self.alert(alert);
+    }
+  }
+
+  /// Also send alerts to these sinks.
+  pub fn send_alerts_to(&mut self, sinks: AlertSinks) {
+    self.alert_sinks = Some(sinks);
+  }
+
+  /// For closing them on shutdown, later alerts only go to the browser.
+  pub fn take_alert_sinks(&mut self) -> Option<AlertSinks> {
+    self.alert_sinks.take()
+  }
+
+  fn alert(&mut self, alert: Alert) {
+    if !self.alert_limiter.allow(Instant::now(), &alert) {
+      return;
+    }
+
+    match alert.severity {
+      Severity::Info => info!("{}", alert.message),
+      Severity::Warning | Severity::Critical => warn!("{}", alert.message),
+    }
+
+    if let Some(sinks) = &mut self.alert_sinks {
+      sinks.send(&alert);
+    }
+
+    if !self.alerts_to_websocket {
+      return;
+    }
+
+    if self.recent_alerts.len() == MAX_RECENT_ALERTS {
+      self.recent_alerts.pop_front();
+    }
+    self.recent_alerts.push_back(alert.clone());
+
+    self.buffer.push(Event::Alert(alert));
+  }
+
+  pub fn count_frame(&mut self) {
+    self.total_frame_count += 1;
+  }
+
+  pub fn bad_frame(&mut self, transmitter_address: Option<MacAddress>) {
+    self.total_bad_frame_count += 1;
+    self.total_bad_frame_count_changed = true;
+
+    // the address could be corrupted too, only blame ones we've seen in good frames
+    if let Some(transmitter_address) = transmitter_address {
+      if self.addresses.contains_key(&transmitter_address) {
+        *self.bad_frame_count.entry(transmitter_address).or_insert(0) += 1;
+        self.changed_bad_frame_counts.insert(transmitter_address);
+      }
+    }
+  }
+
+  pub fn capture_stats(&mut self, stats: CaptureStats) {
+    if let Some(last_stats) = &self.last_capture_stats {
+      // pcap counters are u32 and can wrap around
+      let dropped = stats.dropped.wrapping_sub(last_stats.dropped);
+      let if_dropped = stats.if_dropped.wrapping_sub(last_stats.if_dropped);
+
+      if dropped > 0 || if_dropped > 0 {
+        warn!(
+          "capture dropped {} packets in kernel, {} in interface",
+          dropped, if_dropped
+        );
+      }
+    }
+
+    self.last_capture_stats = Some(stats.clone());
+    self.buffer.push(Event::CaptureStats(stats));
+  }
+
+  pub fn sensor_status(&mut self, name: &str, connected: bool) {
+    self
+      .sensors
+      .entry(name.to_string())
+      .or_insert((connected, None))
+      .0 = connected;
+
+    self.buffer.push(Event::Sensor(name.to_string(), connected));
+  }
+
+  pub fn sensor_stats(&mut self, name: &str, stats: CaptureStats) {
+    self
+      .sensors
+      .entry(name.to_string())
+      .or_insert((true, None))
+      .1 = Some(stats.clone());
+
+    self
+      .buffer
+      .push(Event::SensorStats(name.to_string(), stats));
+  }
+
+  pub fn check_timers(&mut self) {
     let now = Instant::now();
 
+    for (mac, (label, present)) in &mut self.watched {
+      let gone = self.addresses.get(mac).map_or(true, |last_seen| {
+        now.duration_since(*last_seen) >= self.watchlist_timeout
+      });
+      if *present && gone {
+        *present = false;
+        self
+          .buffer
+          .push(Event::Presence(*mac, label.clone(), false));
+      }
+    }
+
+    if let Some(suppressed) = self.alert_limiter.take_suppressed(now) {
+      warn!(
+        "dropped {} duplicate alerts and {} over alerts.max_per_minute",
+        suppressed.duplicates, suppressed.rate_limited
+      );
+    }
+
+    self.random_macs.expire(now);
+    let fake_access_points = self.beacon_flood.take_hidden();
+    if !fake_access_points.is_empty() {
+      for mac in &fake_access_points {
+        self.access_points.remove(mac);
+      }
+      self
+        .buffer
+        .push(Event::FakeAccessPoints(fake_access_points));
+    }
+
     // check beacon quality timers
     for (transmitter_address, next_interval) in &mut self.next_beacon_quality_update {
       if now < *next_interval {
@@ -299,6 +1164,26 @@ impl Store {
 
       self.buffer.push(Event::Rate(*transmitter_address, rate));
 
+      let retry_count = self
+        .retry_count
+        .get(transmitter_address)
+        .copied()
+        .unwrap_or(0);
+      let last_retry_count = self
+        .rate_last_retry_count
+        .insert(*transmitter_address, retry_count)
+        .unwrap_or(0);
+
+      #[allow(clippy::cast_precision_loss)]
+      let retry_rate = if rate == 0 {
+        0.0
+      } else {
+        100.0 * (retry_count - last_retry_count) as f64 / rate as f64
+      };
+      self
+        .buffer
+        .push(Event::RetryRate(*transmitter_address, retry_rate));
+
       if rate == 0 {
         to_remove.push(*transmitter_address);
       } else {
@@ -312,6 +1197,8 @@ impl Store {
 
     // update signals
     let mut to_remove = Vec::new();
+    let mut distances = Vec::new();
+    let mut sensor_signals = Vec::new();
     for (transmitter_address, next_interval) in &mut self.next_signal_event_update {
       // if time is too long, send out of range
 
@@ -319,16 +1206,45 @@ impl Store {
         continue;
       }
 
-      let (signal, time) = self.signals.get(transmitter_address).expect("signals.get");
+      let (signal, antenna_signals, time) =
+        self.signals.get(transmitter_address).expect("signals.get");
 
       if now.duration_since(*time) >= self.signal_event_timeout {
-        self.buffer.push(Event::Signal(*transmitter_address, 0));
+        self
+          .buffer
+          .push(Event::Signal(*transmitter_address, 0, Vec::new()));
+
+        self.positions.remove(transmitter_address);
+        if self.sensor_signals.remove(transmitter_address).is_some() {
+          self
+            .buffer
+            .push(Event::SensorSignals(*transmitter_address, Vec::new()));
+        }
 
         to_remove.push(*transmitter_address);
       } else {
-        self
-          .buffer
-          .push(Event::Signal(*transmitter_address, *signal));
+        self.buffer.push(Event::Signal(
+          *transmitter_address,
+          *signal,
+          antenna_signals.clone(),
+        ));
+
+        distances.push((*transmitter_address, *signal));
+
+        if let Some(readings) = self.sensor_signals.get(transmitter_address) {
+          let signals = fresh_sensor_signals(readings, now, self.signal_event_timeout);
+          self
+            .buffer
+            .push(Event::SensorSignals(*transmitter_address, signals.clone()));
+          sensor_signals.push((*transmitter_address, signals));
+        }
+
+        if let Some(history) = self.signal_histories.get(transmitter_address) {
+          self.buffer.push(Event::SignalStats(
+            *transmitter_address,
+            history.all_stats(now),
+          ));
+        }
 
         *next_interval = now + self.rate_event_update_interval;
       }
@@ -337,5 +1253,142 @@ impl Store {
     for key in to_remove {
       self.next_signal_event_update.remove(&key);
     }
+
+    for (transmitter_address, signal) in distances {
+      self.push_distance(transmitter_address, signal);
+    }
+
+    for (transmitter_address, signals) in sensor_signals {
+      self.push_position(transmitter_address, &signals);
+    }
+
+    // phy changes
+    for (transmitter_address, next_interval) in &mut self.next_phy_event_update {
+      if now < *next_interval {
+        continue;
+      }
+      *next_interval = now + self.phy_event_update_interval;
+
+      let (info, changed) = self
+        .phys
+        .get_mut(transmitter_address)
+        .expect("phys.get_mut");
+
+      if *changed {
+        *changed = false;
+        self
+          .buffer
+          .push(Event::Phy(*transmitter_address, info.clone()));
+      }
+    }
+
+    // throughput, sent once more as empty after going quiet
+    if now >= self.next_throughput_update {
+      self.next_throughput_update = now + self.throughput_update_interval;
+
+      for ((transmitter_address, receiver_address), (recent, total, sent)) in &mut self.link_traffic
+      {
+        if recent.is_empty() && !*sent {
+          continue;
+        }
+        *sent = !recent.is_empty();
+
+        self.buffer.push(Event::Throughput(
+          *transmitter_address,
+          *receiver_address,
+          Throughput::new(mem::take(recent), total.clone()),
+        ));
+      }
+    }
+
+    // airtime, sent once more as 0 after going quiet
+    let elapsed = now.duration_since(self.last_airtime_update);
+    if elapsed >= self.airtime_update_interval {
+      self.last_airtime_update = now;
+
+      // stations that can't hear each other can overlap, adding up to more than 100%
+      let busy =
+        |airtime: Duration| (100.0 * airtime.as_secs_f64() / elapsed.as_secs_f64()).min(100.0);
+
+      for (frequency, (airtime, frames)) in &self.channel_airtime {
+        self.buffer.push(Event::ChannelUtilization(
+          *frequency,
+          ChannelUtilization {
+            busy: busy(*airtime),
+            airtime_us: u64::try_from(airtime.as_micros()).unwrap_or(u64::MAX),
+            frames: *frames,
+          },
+        ));
+      }
+      self
+        .channel_airtime
+        .retain(|_frequency, (_airtime, frames)| *frames > 0);
+      for (airtime, frames) in self.channel_airtime.values_mut() {
+        *airtime = Duration::ZERO;
+        *frames = 0;
+      }
+
+      for (transmitter_address, airtime) in &self.transmitter_airtime {
+        self
+          .buffer
+          .push(Event::Airtime(*transmitter_address, busy(*airtime)));
+      }
+      self
+        .transmitter_airtime
+        .retain(|_transmitter_address, airtime| !airtime.is_zero());
+      for airtime in self.transmitter_airtime.values_mut() {
+        *airtime = Duration::ZERO;
+      }
+    }
+
+    // bad frame counts
+    if self.total_bad_frame_count_changed && now >= self.next_bad_frames_update {
+      self.next_bad_frames_update = now + self.bad_frames_update_interval;
+      self.total_bad_frame_count_changed = false;
+
+      for transmitter_address in self.changed_bad_frame_counts.drain() {
+        let count = self.bad_frame_count[&transmitter_address];
+        self
+          .buffer
+          .push(Event::BadFrames(transmitter_address, count));
+      }
+
+      self.buffer.push(Event::TotalBadFrames(
+        self.total_bad_frame_count,
+        self.total_frame_count,
+      ));
+    }
+
+    // frame subtype counts
+    if self.frame_stats_changed && now >= self.next_frame_stats_update {
+      self.next_frame_stats_update = now + self.frame_stats_update_interval;
+      self.frame_stats_changed = false;
+
+      for transmitter_address in self.changed_frame_stats.drain() {
+        let counts = self.transmitter_frame_subtype_counts[&transmitter_address].clone();
+        self
+          .buffer
+          .push(Event::FrameStats(transmitter_address, counts));
+      }
+
+      self
+        .buffer
+        .push(Event::TotalFrameStats(self.frame_subtype_counts.clone()));
+    }
   }
 }
+
+fn fresh_sensor_signals(
+  readings: &HashMap<String, (i8, Instant)>,
+  now: Instant,
+  timeout: Duration,
+) -> Vec<SensorSignal> {
+  readings
+    .iter()
+    .filter(|(_sensor, (_signal, time))| now.duration_since(*time) < timeout)
+    .map(|(sensor, (signal, _time))| SensorSignal {
+      sensor: sensor.clone(),
+      signal: *signal,
+    })
+    .collect()
+}
@@ -0,0 +1,87 @@
+use ieee80211::FrameLayer;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Traffic {
+  pub frames: u64,
+  pub bytes: u64,
+  /// frames with the Retry bit set
+  pub retries: u64,
+}
+
+/// Traffic on one (transmitter, receiver) link by frame type.
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct LinkTraffic {
+  pub management: Traffic,
+  pub control: Traffic,
+  pub data: Traffic,
+}
+
+impl LinkTraffic {
+  pub fn add(&mut self, layer: &FrameLayer, length: usize, retry: bool) {
+    let traffic = match layer {
+      FrameLayer::Management(_) => &mut self.management,
+      FrameLayer::Control(_) => &mut self.control,
+      FrameLayer::Data(_) => &mut self.data,
+    };
+
+    traffic.frames += 1;
+    traffic.bytes += length as u64;
+    if retry {
+      traffic.retries += 1;
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.management.frames == 0 && self.control.frames == 0 && self.data.frames == 0
+  }
+
+  /// % of frames of any type that were retries
+  #[allow(clippy::cast_precision_loss)]
+  pub fn retry_rate(&self) -> f64 {
+    let frames = self.management.frames + self.control.frames + self.data.frames;
+    let retries = self.management.retries + self.control.retries + self.data.retries;
+
+    if frames == 0 {
+      0.0
+    } else {
+      100.0 * retries as f64 / frames as f64
+    }
+  }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Throughput {
+  /// since the last event, about a second
+  pub recent: LinkTraffic,
+  pub total: LinkTraffic,
+  /// % of `recent` frames that were retries
+  pub recent_retry_rate: f64,
+  /// % of `total` frames that were retries
+  pub total_retry_rate: f64,
+}
+
+impl Throughput {
+  pub fn new(recent: LinkTraffic, total: LinkTraffic) -> Self {
+    Self {
+      recent_retry_rate: recent.retry_rate(),
+      total_retry_rate: total.retry_rate(),
+      recent,
+      total,
+    }
+  }
+}
+
+#[test]
+fn test_retry_rate() {
+  let mut traffic = LinkTraffic::default();
+  assert!(traffic.retry_rate().abs() < f64::EPSILON);
+
+  traffic.data = Traffic {
+    frames: 3,
+    bytes: 300,
+    retries: 1,
+  };
+  traffic.control.frames = 1;
+  assert!((traffic.retry_rate() - 25.0).abs() < 0.001);
+}
@@ -1,14 +1,152 @@
 use ieee80211::MacAddress;
 
-pub fn hash_macs(mac1: MacAddress, mac2: MacAddress) -> String {
+/// The same pair whichever way round they're given.
+pub fn ordered_pair(mac1: MacAddress, mac2: MacAddress) -> (MacAddress, MacAddress) {
   if mac1 >= mac2 {
-    format!("{}{}", mac1, mac2)
+    (mac1, mac2)
   } else {
-    format!("{}{}", mac2, mac1)
+    (mac2, mac1)
   }
 }
 
+/// Exactly `N` hex bytes separated by `:` or `-`
+fn parse_hex_bytes<const N: usize>(s: &str) -> Option<[u8; N]> {
+  let mut bytes = [0; N];
+
+  let mut parts = s.split(|c| c == ':' || c == '-');
+  for byte in &mut bytes {
+    *byte = u8::from_str_radix(parts.next()?, 16).ok()?;
+  }
+
+  if parts.next().is_some() {
+    return None;
+  }
+
+  Some(bytes)
+}
+
+/// Accepts `aa:bb:cc:dd:ee:ff` and `aa-bb-cc-dd-ee-ff`
+pub fn parse_mac(s: &str) -> Option<[u8; 6]> {
+  parse_hex_bytes(s)
+}
+
+/// The first 3 bytes, `aa:bb:cc` or `aa-bb-cc`
+pub fn parse_oui(s: &str) -> Option<[u8; 3]> {
+  parse_hex_bytes(s)
+}
+
+/// Same as serialized addresses, `aa-bb-cc-dd-ee-ff`
+pub fn format_mac(mac: MacAddress) -> String {
+  mac
+    .as_bytes()
+    .iter()
+    .map(|byte| format!("{:02x}", byte))
+    .collect::<Vec<_>>()
+    .join("-")
+}
+
+/// `Beacon`, `QoSData`, `ACK`, ...
+/// Counted in frame stats, from the type and subtype in the frame control
+/// field.
+pub fn frame_subtype_name(frame: &[u8]) -> &'static str {
+  let Some(frame_control) = frame.first() else {
+    return "Unknown";
+  };
+
+  match ((frame_control >> 2) & 0b11, frame_control >> 4) {
+    (0, 0) => "AssociationRequest",
+    (0, 1) => "AssociationResponse",
+    (0, 2) => "ReassociationRequest",
+    (0, 3) => "ReassociationResponse",
+    (0, 4) => "ProbeRequest",
+    (0, 5) => "ProbeResponse",
+    (0, 6) => "TimingAdvertisement",
+    (0, 8) => "Beacon",
+    (0, 9) => "ATIM",
+    (0, 10) => "Disassociate",
+    (0, 11) => "Authentication",
+    (0, 12) => "Deauthentication",
+    (0, 13) => "Action",
+    (0, 14) => "ActionNoAck",
+
+    (1, 4) => "Beamforming",
+    (1, 5) => "VHTNDPAnnouncement",
+    (1, 6) => "ControlFrameExtension",
+    (1, 7) => "ControlWrapper",
+    (1, 8) => "BlockAckRequest",
+    (1, 9) => "BlockAck",
+    (1, 10) => "PSPoll",
+    (1, 11) => "RTS",
+    (1, 12) => "CTS",
+    (1, 13) => "ACK",
+    (1, 14) => "CFEnd",
+    (1, 15) => "CFEndCFAck",
+
+    (2, 0) => "Data",
+    (2, 1) => "DataCFAck",
+    (2, 2) => "DataCFPoll",
+    (2, 3) => "DataCFAckCFPoll",
+    (2, 4) => "Null",
+    (2, 5) => "CFAck",
+    (2, 6) => "CFPoll",
+    (2, 7) => "CFAckCFPoll",
+    (2, 8) => "QoSData",
+    (2, 9) => "QoSDataCFAck",
+    (2, 10) => "QoSDataCFPoll",
+    (2, 11) => "QoSDataCFAckCFPoll",
+    (2, 12) => "QoSNull",
+    (2, 14) => "QoSCFPoll",
+    (2, 15) => "QoSCFAckCFPoll",
+
+    (3, _) => "Extension",
+    _ => "Reserved",
+  }
+}
+
+/// From the sequence control field of management and data frames, `None` for
+/// control frames which don't have one.
+pub fn sequence_number(frame: &[u8]) -> Option<u16> {
+  // type bits of the frame control field
+  if (frame.first()? >> 2) & 0b11 == 1 {
+    return None;
+  }
+
+  let sequence_control = frame.get(22..24)?;
+  Some(u16::from_le_bytes([sequence_control[0], sequence_control[1]]) >> 4)
+}
+
+/// Status code of a (re)association response, 0 is success.
+pub fn association_status(frame: &[u8]) -> Option<u16> {
+  // after the header and capability information
+  let status = frame.get(26..28)?;
+  Some(u16::from_le_bytes([status[0], status[1]]))
+}
+
 pub fn is_broadcast(mac: MacAddress) -> bool {
   // multicast
   (mac.as_bytes()[0] & 0b01) != 0
 }
+
+/// Not from a vendor's range, what phones use for random addresses.
+pub fn is_locally_administered(mac: MacAddress) -> bool {
+  (mac.as_bytes()[0] & 0b10) != 0
+}
+
+/// For tests, panics on a bad address.
+#[cfg(test)]
+pub fn test_mac(bytes: [u8; 6]) -> MacAddress {
+  MacAddress::from_bytes(&bytes).unwrap()
+}
+
+#[test]
+fn test_sequence_number() {
+  let mut beacon = vec![0x80, 0];
+  beacon.resize(22, 0);
+  beacon.extend_from_slice(&(5_u16 << 4).to_le_bytes());
+  assert_eq!(sequence_number(&beacon), Some(5));
+
+  // a block ack is long enough, but those bytes are its payload
+  let mut block_ack = beacon.clone();
+  block_ack[0] = 0x94;
+  assert_eq!(sequence_number(&block_ack), None);
+}
@@ -0,0 +1,140 @@
+use super::{parse_mac, parse_oui};
+use crate::error::{bail, err_msg, Result};
+use ieee80211::MacAddress;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Something we care about, exactly one of `mac`, `oui` or `ssid`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WatchEntry {
+  /// shown instead of the address
+  pub label: String,
+  pub mac: Option<String>,
+  /// first 3 bytes, `aa:bb:cc`
+  pub oui: Option<String>,
+  /// access points with this SSID
+  pub ssid: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchlistConfig {
+  /// gone when not heard from for this long
+  pub timeout_secs: u64,
+  pub entries: Vec<WatchEntry>,
+}
+
+impl Default for WatchlistConfig {
+  fn default() -> Self {
+    Self {
+      timeout_secs: 60,
+      entries: Vec::new(),
+    }
+  }
+}
+
+impl WatchlistConfig {
+  pub fn validate(&self) -> Result<()> {
+    Watchlist::new(self).map(|_| ())
+  }
+}
+
+/// Labels by exact address, vendor prefix or SSID.
+#[derive(Default)]
+pub struct Watchlist {
+  macs: HashMap<[u8; 6], String>,
+  ouis: HashMap<[u8; 3], String>,
+  ssids: HashMap<Vec<u8>, String>,
+}
+
+impl Watchlist {
+  pub fn new(config: &WatchlistConfig) -> Result<Self> {
+    let mut watchlist = Self::default();
+
+    for entry in &config.entries {
+      let label = entry.label.clone();
+
+      match (&entry.mac, &entry.oui, &entry.ssid) {
+        (Some(mac), None, None) => {
+          let mac =
+            parse_mac(mac).ok_or_else(|| err_msg(format!("bad watchlist mac {:?}", mac)))?;
+          watchlist.macs.insert(mac, label);
+        }
+
+        (None, Some(oui), None) => {
+          let oui =
+            parse_oui(oui).ok_or_else(|| err_msg(format!("bad watchlist oui {:?}", oui)))?;
+          watchlist.ouis.insert(oui, label);
+        }
+
+        (None, None, Some(ssid)) => {
+          watchlist.ssids.insert(ssid.as_bytes().to_vec(), label);
+        }
+
+        _ => bail!(
+          "watchlist entry {:?} needs exactly one of mac, oui or ssid",
+          entry.label
+        ),
+      }
+    }
+
+    Ok(watchlist)
+  }
+
+  /// An exact match wins over the vendor prefix.
+  pub fn mac_label(&self, mac: MacAddress) -> Option<&str> {
+    let bytes = mac.as_bytes();
+    let mut oui = [0; 3];
+    oui.copy_from_slice(&bytes[..3]);
+
+    <[u8; 6]>::try_from(bytes)
+      .ok()
+      .and_then(|exact| self.macs.get(&exact))
+      .or_else(|| self.ouis.get(&oui))
+      .map(String::as_str)
+  }
+
+  pub fn ssid_label(&self, ssid: &[u8]) -> Option<&str> {
+    self.ssids.get(ssid).map(String::as_str)
+  }
+}
+
+#[test]
+fn test_watchlist() {
+  use super::test_mac;
+
+  let entry = |label: &str, mac: Option<&str>, oui: Option<&str>, ssid: Option<&str>| WatchEntry {
+    label: label.to_string(),
+    mac: mac.map(str::to_string),
+    oui: oui.map(str::to_string),
+    ssid: ssid.map(str::to_string),
+  };
+
+  let watchlist = Watchlist::new(&WatchlistConfig {
+    entries: vec![
+      entry("CEO laptop", Some("00:11:22:33:44:55"), None, None),
+      entry("lab devices", None, Some("00-11-22"), None),
+      entry("lab test AP", None, None, Some("lab")),
+    ],
+    ..WatchlistConfig::default()
+  })
+  .unwrap();
+
+  assert_eq!(
+    watchlist.mac_label(test_mac([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])),
+    Some("CEO laptop")
+  );
+  assert_eq!(
+    watchlist.mac_label(test_mac([0x00, 0x11, 0x22, 0, 0, 1])),
+    Some("lab devices")
+  );
+  assert_eq!(watchlist.mac_label(test_mac([0x02, 0, 0, 0, 0, 1])), None);
+  assert_eq!(watchlist.ssid_label(b"lab"), Some("lab test AP"));
+
+  assert!(Watchlist::new(&WatchlistConfig {
+    entries: vec![entry("both", Some("00:11:22:33:44:55"), None, Some("lab"))],
+    ..WatchlistConfig::default()
+  })
+  .is_err());
+}
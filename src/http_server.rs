@@ -1,32 +1,118 @@
-use crate::{packet_capture::CaptureType, thread, websocket};
-use futures::executor::block_on;
-use log::{debug, info};
-use std::net::SocketAddr;
-use warp::{path::FullPath, Filter};
+use crate::{
+  error::Result,
+  events::{parse_mac, vendor, Store},
+  pipeline::Pipeline,
+  websocket,
+};
+use futures::prelude::*;
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use std::{net::SocketAddr, time::Duration};
+use tokio::{sync::mpsc, time};
+use warp::{http::StatusCode, path::FullPath, reply::Reply, Filter};
 
 include!(concat!(env!("OUT_DIR"), "/nodejs_bundle.rs"));
 
-pub async fn start(addr: SocketAddr, capture_type: CaptureType) {
-  info!("starting http/websocket server on http://{}/", addr);
+// how long websockets get to send their close message on shutdown
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize)]
+struct SignalQuery {
+  /// seconds of sparkline to return
+  window: Option<u64>,
+}
+
+fn signal_reply(pipeline: &Pipeline, mac: &str, query: &SignalQuery) -> warp::reply::Response {
+  let Some(mac) = parse_mac(mac) else {
+    return warp::reply::with_status("bad mac address", StatusCode::BAD_REQUEST).into_response();
+  };
+  let window = Duration::from_secs(query.window.unwrap_or(10 * 60));
+
+  match pipeline.with_store(|store| store.signal_history(mac, window)) {
+    Some(report) => warp::reply::json(&report).into_response(),
+    None => {
+      warp::reply::with_status("no signal for mac address", StatusCode::NOT_FOUND).into_response()
+    }
+  }
+}
+
+fn frame_stats_reply(pipeline: &Pipeline, mac: &str) -> warp::reply::Response {
+  let Some(mac) = parse_mac(mac) else {
+    return warp::reply::with_status("bad mac address", StatusCode::BAD_REQUEST).into_response();
+  };
+
+  match pipeline.with_store(|store| store.frame_stats(mac)) {
+    Some(counts) => warp::reply::json(&serde_json::json!({
+      "vendor": vendor(&mac),
+      "counts": counts,
+    }))
+    .into_response(),
+    None => {
+      warp::reply::with_status("no frames from mac address", StatusCode::NOT_FOUND).into_response()
+    }
+  }
+}
+
+/// Binds the server, the returned future runs until `pipeline` shuts down.
+pub fn start(addr: SocketAddr, pipeline: Pipeline) -> Result<impl Future<Output = ()>> {
+  let mut shutdown_receiver = pipeline.shutdown_receiver();
 
-  let routes = warp::path("ws")
-    .and(warp::ws())
-    .map(move |ws: warp::ws::Ws| {
-      let capture_type = capture_type.clone();
-      ws.on_upgrade(move |ws| {
-        async {
-          // we don't want to use tokio here because iterator streams
-          // block the other http request futures by taking from the pool
-          thread::spawn("websocket future thread", move || {
-            block_on(websocket::start(ws, capture_type)).expect("block_on");
-          });
-        }
-      })
-    })
+  // every websocket holds a sender, recv() returns None once they're all gone
+  let (clients_sender, mut clients_receiver) = mpsc::channel::<()>(1);
+
+  let signal = warp::path!("api" / "signal" / String)
+    .and(warp::query::<SignalQuery>())
+    .map({
+      let pipeline = pipeline.clone();
+      move |mac: String, query: SignalQuery| signal_reply(&pipeline, &mac, &query)
+    });
+
+  let total_frame_stats = warp::path!("api" / "frames").map({
+    let pipeline = pipeline.clone();
+    move || warp::reply::json(&pipeline.with_store(Store::total_frame_stats))
+  });
+
+  let frame_stats = warp::path!("api" / "frames" / String).map({
+    let pipeline = pipeline.clone();
+    move |mac: String| frame_stats_reply(&pipeline, &mac)
+  });
+
+  let api = signal.or(total_frame_stats).or(frame_stats);
+
+  let routes = api
+    .or(
+      warp::path("ws")
+        .and(warp::ws())
+        .map(move |ws: warp::ws::Ws| {
+          let pipeline = pipeline.clone();
+          let clients_sender = clients_sender.clone();
+          ws.on_upgrade(move |ws| async move {
+            if let Err(e) = websocket::start(ws, pipeline).await {
+              error!("websocket error: {}", e);
+            }
+            drop(clients_sender);
+          })
+        }),
+    )
     .or(warp::path::full().map(|path: FullPath| {
       debug!("http {}", path.as_str());
       NODEJS_BUNDLE.as_warp_reply(path)
     }));
 
-  warp::serve(routes).bind(addr).await;
+  let (addr, server) = warp::serve(routes).try_bind_with_graceful_shutdown(addr, async move {
+    let _ = shutdown_receiver.changed().await;
+  })?;
+
+  info!("starting http/websocket server on http://{}/", addr);
+
+  Ok(async move {
+    server.await;
+
+    if time::timeout(CLOSE_TIMEOUT, clients_receiver.recv())
+      .await
+      .is_err()
+    {
+      warn!("websockets didn't close in time");
+    }
+  })
 }
@@ -1,22 +1,34 @@
 #![warn(clippy::pedantic)]
 
+mod alert_sinks;
+mod config;
 mod error;
 mod events;
 mod http_server;
 mod logger;
 mod packet_capture;
+mod pipeline;
+#[cfg(target_os = "linux")]
+mod privileges;
+mod sensor;
+mod shutdown;
 mod thread;
 mod websocket;
 
 use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener},
+    process,
     time::Duration,
 };
 
 use clap::{ArgAction, Parser};
-use log::debug;
+use log::{debug, info};
+use tokio::runtime::Runtime;
 
-use crate::{error::Result, packet_capture::CaptureType};
+use crate::{config::Config, error::Result, packet_capture::CaptureType, pipeline::Pipeline};
+
+// not one of the 128 + signal codes
+const CAPTURE_FAILED_EXIT_CODE: i32 = 1;
 
 /// wifi-visualizer
 #[derive(Debug, Parser)]
@@ -30,24 +42,51 @@ pub struct Args {
     #[arg(short, long)]
     pub no_browser: bool,
 
+    /// TOML file with extra settings
+    #[arg(short, long)]
+    pub config: Option<String>,
+
     /// Don't play back files at original speed
     #[arg(long, requires("file"))]
     pub no_sleep_playback: bool,
 
     /// File to read from
-    #[arg(short, long, required(true), conflicts_with("interface"))]
+    #[arg(
+        short,
+        long,
+        required_unless_present("collect"),
+        conflicts_with("interface")
+    )]
     pub file: Option<String>,
 
     /// Interface to capture packets from
-    #[arg(short, long, required(true), conflicts_with("file"))]
+    #[arg(
+        short,
+        long,
+        required_unless_present("collect"),
+        conflicts_with("file")
+    )]
     pub interface: Option<String>,
-}
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
-    let http_server_addr = SocketAddr::new(ip, 8000);
+    /// Stream packets to the collector at this address instead of showing them
+    #[arg(long, value_name("ADDR"), conflicts_with("collect"))]
+    pub sensor: Option<String>,
+
+    /// Name shown for this sensor on the collector, defaults to its ip address
+    #[arg(long, value_name("NAME"), requires("sensor"))]
+    pub sensor_name: Option<String>,
+
+    /// Show packets from sensors that connect to this address
+    #[arg(long, value_name("ADDR"), conflicts_with_all(["file", "interface"]))]
+    pub collect: Option<String>,
+
+    /// Switch to this user after opening the interface
+    #[cfg(target_os = "linux")]
+    #[arg(short, long, requires("interface"))]
+    pub user: Option<String>,
+}
 
+fn main() -> Result<()> {
     let args = Args::parse();
 
     #[cfg(debug_assertions)]
@@ -56,7 +95,18 @@ async fn main() -> Result<()> {
     #[cfg(not(debug_assertions))]
     logger::initialize(args.verbose >= 1, args.verbose >= 2);
 
-    let capture_type = if let Some(file) = args.file {
+    let config = if let Some(path) = &args.config {
+        debug!("loading config {:?}", path);
+        Config::load(path)?
+    } else {
+        Config::default()
+    };
+    // not while the store is locked on the first new address
+    events::load_oui_csv(&config.oui_csv)?;
+
+    let capture_type = if let Some(addr) = args.collect {
+        CaptureType::Collector(TcpListener::bind(addr)?)
+    } else if let Some(file) = args.file {
         debug!("got input file {:?}", file);
 
         if file == "-" {
@@ -83,13 +133,40 @@ async fn main() -> Result<()> {
             }
         }
 
-        CaptureType::Interface(interface_name)
+        let capture = packet_capture::open_live_capture(&interface_name)?;
+
+        // runtime threads aren't spawned yet, so this covers the whole process
+        #[cfg(target_os = "linux")]
+        privileges::drop_privileges(args.user.as_deref())?;
+
+        CaptureType::Live(capture)
     } else {
         unreachable!()
     };
 
+    let runtime = Runtime::new()?;
+    let exit_code = if let Some(collector_addr) = args.sensor {
+        runtime.block_on(run_sensor(capture_type, collector_addr, args.sensor_name))?
+    } else {
+        runtime.block_on(run(capture_type, config, args.no_browser))?
+    };
+
+    // the capture reader might be stuck waiting for a packet
+    runtime.shutdown_timeout(Duration::from_secs(1));
+
+    process::exit(exit_code);
+}
+
+async fn run(capture_type: CaptureType, config: Config, no_browser: bool) -> Result<i32> {
+    let ip = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    let http_server_addr = SocketAddr::new(ip, 8000);
+
+    let (pipeline, mut pipeline_task) = Pipeline::start(capture_type, &config)?;
+
+    let server = tokio::spawn(http_server::start(http_server_addr, pipeline.clone())?);
+
     // TODO wait until packet capture begins successfully?
-    if !args.no_browser {
+    if !no_browser {
         tokio::spawn(async move {
             tokio::time::sleep(Duration::from_millis(100)).await;
 
@@ -97,7 +174,51 @@ async fn main() -> Result<()> {
         });
     }
 
-    http_server::start(http_server_addr, capture_type).await;
+    let signal = tokio::select! {
+        result = &mut pipeline_task => {
+            if result?.is_err() {
+                // already logged and shown in the browser
+                pipeline.shutdown();
+                server.await?;
+                return Ok(CAPTURE_FAILED_EXIT_CODE);
+            }
+
+            // a file read to the end is still worth looking at
+            let signal = shutdown::wait_for_signal().await?;
+            info!("received {}, shutting down", signal);
+            pipeline.shutdown();
+            signal
+        }
 
-    Ok(())
+        result = shutdown::wait_for_signal() => {
+            let signal = result?;
+            info!("received {}, shutting down", signal);
+            pipeline.shutdown();
+            // a capture error by now doesn't change why we're exiting
+            let _capture_result = pipeline_task.await?;
+            signal
+        }
+    };
+    server.await?;
+
+    Ok(signal.exit_code())
+}
+
+async fn run_sensor(
+    capture_type: CaptureType,
+    collector_addr: String,
+    name: Option<String>,
+) -> Result<i32> {
+    tokio::select! {
+        result = sensor::run(capture_type, collector_addr, name) => {
+            result?;
+            Ok(0)
+        }
+
+        result = shutdown::wait_for_signal() => {
+            let signal = result?;
+            info!("received {}, shutting down", signal);
+            Ok(signal.exit_code())
+        }
+    }
 }
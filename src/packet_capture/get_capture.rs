@@ -24,7 +24,8 @@ pub fn get_live_capture(dev: Device) -> Result<Capture<Active>> {
     Capture::from_device(dev)?
       .immediate_mode(true)
       .promisc(true)
-      // .timeout(1000)
+      // wake up the reader so stats are polled and closed streams are noticed
+      .timeout(1000)
       .open()?,
   )
 }
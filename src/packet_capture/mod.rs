@@ -1,22 +1,49 @@
 mod get_capture;
 
 use self::get_capture::{get_file_capture, get_interface, get_live_capture, get_stdin_capture};
-use crate::error::{bail, Result};
-use futures::prelude::*;
+use crate::{
+  error::{bail, Error, Result},
+  sensor, thread,
+};
+use futures::{prelude::*, stream::BoxStream};
 use ieee80211::Frame;
-use pcap::{linktypes, Activated, Capture, Error as PcapError};
-use radiotap::Radiotap;
-use std::{borrow::Cow, thread, time::Duration};
+use log::{debug, warn};
+use pcap::{linktypes, Activated, Active, Capture, Error as PcapError};
+use radiotap::{
+  field::{self, Field, Kind},
+  Radiotap, RadiotapIterator,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  borrow::Cow,
+  net::TcpListener,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+const STATS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// packets waiting to be decoded, the reader thread blocks when this is full
+const PACKET_CHANNEL_CAPACITY: usize = 1024;
 
-#[derive(Clone)]
 pub enum CaptureType {
   Stdin,
   File(String, bool), // path, sleep_playback
-  Interface(String),
+  // opened up front so privileges can be dropped before anything else runs
+  Live(Capture<Active>),
+  // packets streamed from sensors, bound up front like Live
+  Collector(TcpListener),
+}
+
+pub fn open_live_capture(interface_name: &str) -> Result<Capture<Active>> {
+  let device = get_interface(interface_name)?;
+  get_live_capture(device)
 }
 
 fn get_capture_iterator(capture_type: CaptureType) -> Result<CaptureIterator> {
   let mut sleep_playback = false;
+  let mut is_live = false;
 
   let capture = match capture_type {
     CaptureType::Stdin => get_stdin_capture()?.into(),
@@ -24,63 +51,235 @@ fn get_capture_iterator(capture_type: CaptureType) -> Result<CaptureIterator> {
       sleep_playback = sleep_playback2;
       get_file_capture(path)?.into()
     }
-    CaptureType::Interface(interface_name) => {
-      let device = get_interface(&interface_name)?;
-      get_live_capture(device)?.into()
+    CaptureType::Live(capture) => {
+      is_live = true;
+      capture.into()
     }
+    CaptureType::Collector(_) => bail!("can't read packets from a collector"),
   };
 
-  CaptureIterator::new(capture, sleep_playback)
+  CaptureIterator::new(capture, sleep_playback, is_live)
 }
 
 pub struct FrameWithRadiotap<'a> {
   pub id: u64,
   pub frame: Frame<'a>,
   pub radiotap: Option<Radiotap>,
+  /// radiotap flagged a bad FCS, or the FCS didn't match
+  pub bad_fcs: bool,
+  /// combined signal of all antennas
+  pub signal: Option<i8>,
+  pub antenna_signals: Vec<AntennaSignal>,
+  /// bytes on the air, including the FCS when there's radiotap
+  pub length: usize,
+  /// which sensor heard it, when collecting
+  pub sensor: Option<Arc<str>>,
+  /// when it was captured, since the unix epoch
+  pub timestamp: Duration,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AntennaSignal {
+  pub antenna: u8,
+  pub signal: i8,
+}
+
+/// pcap counters, only available on live captures
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CaptureStats {
+  /// packets received by the filter
+  pub received: u32,
+  /// packets dropped because the kernel buffer was full
+  pub dropped: u32,
+  /// packets dropped by the interface/driver
+  pub if_dropped: u32,
+}
+
+pub enum CaptureItem<'a> {
+  Frame(FrameWithRadiotap<'a>),
+  /// the capture stopped working, like an interface that went away, nothing
+  /// follows
+  Failed(Error),
+  Stats(CaptureStats),
+  SensorStats(Arc<str>, CaptureStats),
+  // name, connected
+  SensorStatus(Arc<str>, bool),
+}
+
+pub enum Packet {
+  Data(Duration, Vec<u8>), // timestamp, bytes
+  Stats(CaptureStats),
 }
 
 pub async fn get_capture_stream(
   capture_type: CaptureType,
-) -> Result<impl Stream<Item = Result<FrameWithRadiotap<'static>>>> {
+) -> Result<BoxStream<'static, Result<CaptureItem<'static>>>> {
+  if let CaptureType::Collector(listener) = capture_type {
+    return Ok(sensor::collector::get_collector_stream(listener)?.boxed());
+  }
+
+  let (is_radiotap, packets) = get_packet_stream(capture_type)?;
+
+  let mut id = 0;
+
+  Ok(
+    packets
+      .map(move |result| match result {
+        // decoding happens here, so this is pcap
+        Err(e) => Ok(CaptureItem::Failed(e)),
+
+        Ok(Packet::Stats(stats)) => Ok(CaptureItem::Stats(stats)),
+
+        Ok(Packet::Data(timestamp, bytes)) => {
+          id += 1;
+          decode_packet(id, is_radiotap, timestamp, bytes, None).map(CaptureItem::Frame)
+        }
+      })
+      .boxed(),
+  )
+}
+
+/// Undecoded packets, and whether they start with a radiotap header.
+pub fn get_packet_stream(
+  capture_type: CaptureType,
+) -> Result<(bool, impl Stream<Item = Result<Packet>>)> {
   let capture_iterator = get_capture_iterator(capture_type)?;
   let is_radiotap = capture_iterator.is_radiotap;
 
-  let mut id = 0;
+  let (sender, receiver) = mpsc::channel(PACKET_CHANNEL_CAPACITY);
+
+  // pcap only has blocking reads, so keep them off the runtime
+  thread::spawn("capture reader", move || {
+    for result in capture_iterator {
+      if sender.blocking_send(result).is_err() {
+        debug!("capture stream dropped, stopping capture");
+        return;
+      }
+    }
+
+    debug!("capture finished");
+  });
+
+  let packets = stream::unfold(receiver, |mut receiver| async move {
+    let result = receiver.recv().await?;
+    Some((result, receiver))
+  });
+
+  Ok((is_radiotap, packets))
+}
+
+pub fn decode_packet(
+  id: u64,
+  is_radiotap: bool,
+  timestamp: Duration,
+  bytes: Vec<u8>,
+  sensor: Option<Arc<str>>,
+) -> Result<FrameWithRadiotap<'static>> {
+  let mut bad_fcs = false;
+  let mut signal = None;
+  let mut antenna_signals = Vec::new();
+  let mut length = bytes.len();
+
+  let (radiotap, bytes) = if is_radiotap {
+    let (radiotap, rest) = Radiotap::parse(&bytes)?;
+
+    // a namespace we can't walk only loses the per-antenna signals
+    (signal, antenna_signals) = parse_signals(&bytes).unwrap_or_else(|e| {
+      debug!("parse_signals: {}", e);
+      (None, Vec::new())
+    });
+    if signal.is_none() {
+      signal = radiotap.antenna_signal.as_ref().map(|signal| signal.value);
+    }
+
+    let has_fcs = radiotap.flags.map_or(false, |flags| flags.fcs);
+    if radiotap.flags.map_or(false, |flags| flags.bad_fcs) {
+      bad_fcs = true;
+    }
+
+    length = rest.len() + if has_fcs { 0 } else { 4 };
+
+    let frame_bytes = if has_fcs {
+      if rest.len() < 4 {
+        bail!("frame too short for fcs");
+      }
+
+      // remove last 4 bytes (uint32_t)
+      let (data, fcs) = rest.split_at(rest.len() - 4);
+      if !check_fcs(data, fcs) {
+        bad_fcs = true;
+      }
+
+      data
+    } else {
+      rest
+    };
+
+    (Some(radiotap), Cow::Borrowed(frame_bytes))
+  } else {
+    (None, Cow::Owned(bytes))
+  };
 
-  Ok(stream::iter(capture_iterator).map(move |result| {
-    match result {
-      Err(e) => Err(e),
-
-      Ok(bytes) => {
-        let (radiotap, bytes) = if is_radiotap {
-          let (radiotap, rest) = Radiotap::parse(&bytes)?;
-
-          let has_fcs = radiotap.flags.map_or(false, |flags| flags.fcs);
-
-          let frame_bytes = if has_fcs {
-            // remove last 4 bytes (uint32_t)
-            let (data, _fcs) = rest.split_at(rest.len() - 4);
-            data
-          } else {
-            rest
-          };
-
-          (Some(radiotap), Cow::Borrowed(frame_bytes))
-        } else {
-          (None, Cow::Owned(bytes))
-        };
-
-        let frame = Frame::new(bytes.into_owned());
-        id += 1;
-
-        Ok(FrameWithRadiotap {
-          id,
-          frame,
-          radiotap,
-        })
+  let frame = Frame::new(bytes.into_owned());
+
+  Ok(FrameWithRadiotap {
+    id,
+    frame,
+    radiotap,
+    bad_fcs,
+    signal,
+    antenna_signals,
+    length,
+    sensor,
+    timestamp,
+  })
+}
+
+/// Multi-antenna adapters add a radiotap namespace per antenna, each with a
+/// signal followed by its antenna index. A signal without an index is the
+/// combined one.
+fn parse_signals(bytes: &[u8]) -> Result<(Option<i8>, Vec<AntennaSignal>)> {
+  let mut combined = None;
+  let mut antenna_signals = Vec::new();
+  let mut last_signal = None;
+
+  for result in RadiotapIterator::from_bytes(bytes)? {
+    let (kind, data) = result?;
+
+    match kind {
+      Kind::AntennaSignal => {
+        let signal = field::AntennaSignal::from_bytes(data)?.value;
+        if let Some(last_signal) = last_signal.replace(signal) {
+          combined.get_or_insert(last_signal);
+        }
       }
+
+      Kind::Antenna => {
+        if let Some(signal) = last_signal.take() {
+          antenna_signals.push(AntennaSignal {
+            antenna: field::Antenna::from_bytes(data)?.value,
+            signal,
+          });
+        }
+      }
+
+      _ => {}
     }
-  }))
+  }
+
+  if let Some(last_signal) = last_signal {
+    combined.get_or_insert(last_signal);
+  }
+
+  Ok((combined, antenna_signals))
+}
+
+/// 802.11 FCS is a little endian CRC-32 of the whole frame
+fn check_fcs(data: &[u8], fcs: &[u8]) -> bool {
+  let mut expected = [0; 4];
+  expected.copy_from_slice(fcs);
+
+  crc32fast::hash(data) == u32::from_le_bytes(expected)
 }
 
 pub struct CaptureIterator {
@@ -88,10 +287,14 @@ pub struct CaptureIterator {
   is_radiotap: bool,
   sleep_playback: bool,
   maybe_last_time: Option<Duration>,
+  is_live: bool,
+  // after an error pcap won't recover from
+  failed: bool,
+  next_stats_poll: Instant,
 }
 
 impl CaptureIterator {
-  fn new(capture: Capture<dyn Activated>, sleep_playback: bool) -> Result<Self> {
+  fn new(capture: Capture<dyn Activated>, sleep_playback: bool, is_live: bool) -> Result<Self> {
     let maybe_last_time: Option<Duration> = None;
 
     let datalink = capture.get_datalink();
@@ -113,45 +316,145 @@ impl CaptureIterator {
       is_radiotap,
       sleep_playback,
       maybe_last_time,
+      is_live,
+      failed: false,
+      next_stats_poll: Instant::now() + STATS_POLL_INTERVAL,
     })
   }
+
+  fn poll_stats(&mut self) -> Option<CaptureStats> {
+    if !self.is_live {
+      return None;
+    }
+
+    let now = Instant::now();
+    if now < self.next_stats_poll {
+      return None;
+    }
+    self.next_stats_poll = now + STATS_POLL_INTERVAL;
+
+    match self.capture.stats() {
+      Ok(stat) => Some(CaptureStats {
+        received: stat.received,
+        dropped: stat.dropped,
+        if_dropped: stat.if_dropped,
+      }),
+      Err(e) => {
+        warn!("couldn't get capture stats: {}", e);
+        None
+      }
+    }
+  }
 }
 
 impl Iterator for CaptureIterator {
-  type Item = Result<Vec<u8>>;
+  type Item = Result<Packet>;
 
   fn next(&mut self) -> Option<Self::Item> {
-    match self.capture.next() {
-      Err(err) => match err {
-        PcapError::NoMorePackets => None,
-        // PcapError::TimeoutExpired => {
-        //   // this is called on windows at least!
-        //   Some(Ok(None))
-        // }
-        other => {
-          // panic!("{}", err);
-          Some(Err(other.into()))
-        }
-      },
+    if self.failed {
+      return None;
+    }
+
+    loop {
+      if let Some(stats) = self.poll_stats() {
+        return Some(Ok(Packet::Stats(stats)));
+      }
+
+      match self.capture.next() {
+        Err(err) => match err {
+          PcapError::NoMorePackets => return None,
+          PcapError::TimeoutExpired => {
+            // no packets during the read timeout, give stats a chance
+            continue;
+          }
+          // like a truncated file, show what was read
+          other if !self.is_live => {
+            warn!("capture ended early: {}", other);
+            return None;
+          }
+          other => {
+            self.failed = true;
+            return Some(Err(other.into()));
+          }
+        },
 
-      Ok(packet) => {
-        if self.sleep_playback {
+        Ok(packet) => {
           #[allow(clippy::cast_possible_truncation)]
           #[allow(clippy::cast_sign_loss)]
           let current_time = std::time::Duration::new(
             packet.header.ts.tv_sec as u64,
             (packet.header.ts.tv_usec * 1000) as u32,
           );
-          if let Some(last_time) = self.maybe_last_time {
-            if current_time > last_time {
-              thread::sleep(current_time - last_time);
+
+          if self.sleep_playback {
+            if let Some(last_time) = self.maybe_last_time {
+              if current_time > last_time {
+                thread::sleep(current_time - last_time);
+              }
             }
+            self.maybe_last_time = Some(current_time);
           }
-          self.maybe_last_time = Some(current_time);
-        }
 
-        Some(Ok(packet.data.to_owned()))
+          return Some(Ok(Packet::Data(current_time, packet.data.to_owned())));
+        }
       }
     }
   }
 }
+
+#[test]
+fn test_parse_signals() {
+  const ANTENNA_SIGNAL: u32 = 1 << 5;
+  const ANTENNA: u32 = 1 << 11;
+  // another radiotap namespace follows
+  const NEXT: u32 = (1 << 29) | (1 << 31);
+
+  let radiotap = |present: &[u32], fields: &[i8]| {
+    let length = 4 + present.len() * 4 + fields.len();
+    let mut bytes = vec![0, 0];
+    bytes.extend_from_slice(&u16::try_from(length).unwrap().to_le_bytes());
+    for word in present {
+      bytes.extend_from_slice(&word.to_le_bytes());
+    }
+    bytes.extend(fields.iter().map(|field| field.to_le_bytes()[0]));
+    bytes
+  };
+  let antenna = |antenna, signal| AntennaSignal { antenna, signal };
+
+  // combined signal only
+  let bytes = radiotap(&[ANTENNA_SIGNAL], &[-40]);
+  assert_eq!(parse_signals(&bytes).unwrap(), (Some(-40), vec![]));
+
+  // combined, then one namespace per antenna
+  let bytes = radiotap(
+    &[
+      ANTENNA_SIGNAL | NEXT,
+      ANTENNA_SIGNAL | ANTENNA | NEXT,
+      ANTENNA_SIGNAL | ANTENNA,
+    ],
+    &[-40, -42, 0, -38, 1],
+  );
+  assert_eq!(
+    parse_signals(&bytes).unwrap(),
+    (Some(-40), vec![antenna(0, -42), antenna(1, -38)])
+  );
+
+  // no combined signal, and one without an index after the antennas
+  let bytes = radiotap(
+    &[ANTENNA_SIGNAL | ANTENNA | NEXT, ANTENNA_SIGNAL],
+    &[-42, 0, -40],
+  );
+  assert_eq!(
+    parse_signals(&bytes).unwrap(),
+    (Some(-40), vec![antenna(0, -42)])
+  );
+}
+
+#[test]
+fn test_check_fcs() {
+  // CRC-32 check value
+  let fcs = 0xCBF4_3926_u32.to_le_bytes();
+
+  assert!(check_fcs(b"123456789", &fcs));
+  assert!(!check_fcs(b"123456780", &fcs));
+}
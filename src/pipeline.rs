@@ -0,0 +1,193 @@
+use crate::{
+  alert_sinks::AlertSinks,
+  config::Config,
+  error::Result,
+  events::{
+    handle_capture_stats, handle_frame, handle_sensor_stats, handle_sensor_status, Event, Store,
+  },
+  packet_capture::{get_capture_stream, CaptureItem, CaptureType},
+};
+use futures::prelude::*;
+use log::{error, info};
+use std::{
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+use tokio::{
+  sync::{broadcast, watch},
+  task::JoinHandle,
+  time,
+};
+
+// batches of events a subscriber can fall behind before it has to resync
+const EVENTS_CHANNEL_CAPACITY: usize = 1024;
+
+const TIMERS_INTERVAL: Duration = Duration::from_millis(200);
+
+pub type EventsReceiver = broadcast::Receiver<Arc<Vec<Event>>>;
+
+/// One capture and `Store` for the whole process, shared by every client.
+#[derive(Clone)]
+pub struct Pipeline {
+  store: Arc<Mutex<Store>>,
+  events: broadcast::Sender<Arc<Vec<Event>>>,
+  shutdown: Arc<watch::Sender<bool>>,
+}
+
+impl Pipeline {
+  /// The returned task finishes when the capture ends or after `shutdown`,
+  /// with an error if the capture failed.
+  pub fn start(
+    capture_type: CaptureType,
+    config: &Config,
+  ) -> Result<(Self, JoinHandle<Result<()>>)> {
+    let (events, _) = broadcast::channel(EVENTS_CHANNEL_CAPACITY);
+    let (shutdown, _) = watch::channel(false);
+
+    let mut store = Store::new(config);
+    if let Some(sinks) = AlertSinks::spawn(&config.alerts.sinks)? {
+      store.send_alerts_to(sinks);
+    }
+
+    let pipeline = Self {
+      store: Arc::new(Mutex::new(store)),
+      events,
+      shutdown: Arc::new(shutdown),
+    };
+
+    let task = tokio::spawn({
+      let pipeline = pipeline.clone();
+
+      async move {
+        let result = pipeline.run(capture_type).await;
+        if let Err(e) = &result {
+          error!("capture error: {}", e);
+
+          pipeline.publish(|store| {
+            store.error(format!("{}", e));
+            store.flush_buffer()
+          });
+        }
+
+        result
+      }
+    });
+
+    Ok((pipeline, task))
+  }
+
+  /// Stops the capture and tells every subscriber to close.
+  pub fn shutdown(&self) {
+    self.shutdown.send_replace(true);
+  }
+
+  pub fn shutdown_receiver(&self) -> watch::Receiver<bool> {
+    self.shutdown.subscribe()
+  }
+
+  /// Returns events describing the current state, and a receiver for
+  /// everything that happens after it.
+  pub fn subscribe(&self) -> (Vec<Event>, EventsReceiver) {
+    // hold the lock so no events are published between the two
+    let store = self.store.lock().expect("store lock");
+
+    (store.snapshot(), self.events.subscribe())
+  }
+
+  /// Read access to the `Store`, for queries that aren't events.
+  pub fn with_store<F, T>(&self, f: F) -> T
+  where
+    F: FnOnce(&Store) -> T,
+  {
+    let store = self.store.lock().expect("store lock");
+    f(&store)
+  }
+
+  async fn run(&self, capture_type: CaptureType) -> Result<()> {
+    let mut capture_stream = get_capture_stream(capture_type).await?;
+    let mut timers = time::interval(TIMERS_INTERVAL);
+    let mut shutdown_receiver = self.shutdown_receiver();
+    let mut failure = None;
+
+    loop {
+      tokio::select! {
+        maybe_result = capture_stream.next() => {
+          let Some(result) = maybe_result else {
+            break;
+          };
+
+          self.publish(|store| match result {
+            Ok(CaptureItem::Frame(frame_with_radiotap)) => {
+              handle_frame(store, &frame_with_radiotap).unwrap_or_else(|e| {
+                error!("packet parse error: {:?}", e);
+                store.flush_buffer()
+              })
+            }
+
+            Ok(CaptureItem::Failed(e)) => {
+              failure = Some(e);
+              Vec::new()
+            }
+
+            Ok(CaptureItem::Stats(stats)) => handle_capture_stats(store, stats),
+
+            Ok(CaptureItem::SensorStats(name, stats)) => handle_sensor_stats(store, &name, stats),
+
+            Ok(CaptureItem::SensorStatus(name, connected)) => {
+              handle_sensor_status(store, &name, connected)
+            }
+
+            Err(e) => {
+              error!("packet parse error: {:?}", e);
+              Vec::new()
+            }
+          });
+
+          if failure.is_some() {
+            break;
+          }
+        }
+
+        _ = timers.tick() => {
+          self.publish(|store| {
+            store.check_timers();
+            store.flush_buffer()
+          });
+        }
+
+        _ = shutdown_receiver.changed() => {
+          info!("stopping capture");
+          break;
+        }
+      }
+    }
+
+    // dropping the stream stops the reader thread and closes the capture
+    drop(capture_stream);
+    info!("capture finished");
+
+    // not holding the store lock while waiting
+    let sinks = self.store.lock().expect("store lock").take_alert_sinks();
+    if let Some(sinks) = sinks {
+      sinks.close().await;
+    }
+
+    match failure {
+      Some(e) => Err(e),
+      None => Ok(()),
+    }
+  }
+
+  fn publish<F>(&self, f: F)
+  where
+    F: FnOnce(&mut Store) -> Vec<Event>,
+  {
+    let mut store = self.store.lock().expect("store lock");
+
+    let events = f(&mut store);
+    if !events.is_empty() {
+      // an error here only means nobody is subscribed right now
+      let _ = self.events.send(Arc::new(events));
+    }
+  }
+}
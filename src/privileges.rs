@@ -0,0 +1,37 @@
+use crate::error::{err_msg, Result};
+use caps::CapSet;
+use log::{info, warn};
+use nix::unistd::{setgid, setgroups, setuid, Uid, User};
+
+/// Switches to `user` if given, then clears all of our capabilities.
+///
+/// Capabilities are per thread on Linux, so this has to run before any
+/// other thread is spawned for it to apply to the whole process.
+pub fn drop_privileges(user: Option<&str>) -> Result<()> {
+  if let Some(name) = user {
+    let user =
+      User::from_name(name)?.ok_or_else(|| err_msg(format!("user {:?} not found", name)))?;
+
+    // order matters, we can't change groups once we're not root
+    setgroups(&[user.gid])?;
+    setgid(user.gid)?;
+    setuid(user.uid)?;
+
+    info!("switched to user {}", user.name);
+  } else if Uid::effective().is_root() {
+    warn!("still running as root, use --user to switch to an unprivileged user");
+  }
+
+  for cap_set in [
+    CapSet::Ambient,
+    CapSet::Inheritable,
+    CapSet::Effective,
+    CapSet::Permitted,
+  ] {
+    caps::clear(None, cap_set)?;
+  }
+
+  info!("dropped capabilities");
+
+  Ok(())
+}
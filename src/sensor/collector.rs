@@ -0,0 +1,167 @@
+use super::protocol::{self, Message};
+use crate::{
+  error::{bail, Result},
+  packet_capture::{decode_packet, CaptureItem},
+};
+use futures::prelude::*;
+use log::{info, warn};
+use std::{
+  collections::HashSet,
+  net::SocketAddr,
+  sync::{Arc, Mutex},
+};
+use tokio::{
+  io::BufReader,
+  net::{TcpListener, TcpStream},
+  sync::mpsc,
+};
+
+// decoded items from all sensors waiting for the pipeline
+const ITEM_CHANNEL_CAPACITY: usize = 1024;
+
+type ItemSender = mpsc::Sender<Result<CaptureItem<'static>>>;
+
+// of the connected sensors
+type SensorNames = Arc<Mutex<HashSet<Arc<str>>>>;
+
+/// Frames from every sensor that connects to `listener`, tagged with the
+/// sensor's name. Dropping the stream disconnects all sensors.
+pub fn get_collector_stream(
+  listener: std::net::TcpListener,
+) -> Result<impl Stream<Item = Result<CaptureItem<'static>>>> {
+  listener.set_nonblocking(true)?;
+  let listener = TcpListener::from_std(listener)?;
+  info!("collecting from sensors on {}", listener.local_addr()?);
+
+  let (sender, receiver) = mpsc::channel(ITEM_CHANNEL_CAPACITY);
+  tokio::spawn(accept_sensors(listener, sender));
+
+  Ok(stream::unfold(receiver, |mut receiver| async move {
+    let result = receiver.recv().await?;
+    Some((result, receiver))
+  }))
+}
+
+async fn accept_sensors(listener: TcpListener, sender: ItemSender) {
+  let names = SensorNames::default();
+
+  loop {
+    let (stream, addr) = tokio::select! {
+      result = listener.accept() => match result {
+        Ok(accepted) => accepted,
+        Err(e) => {
+          warn!("couldn't accept sensor: {}", e);
+          continue;
+        }
+      },
+
+      () = sender.closed() => break,
+    };
+
+    let sender = sender.clone();
+    let names = names.clone();
+    tokio::spawn(async move {
+      if let Err(e) = handle_sensor(stream, addr, &sender, &names).await {
+        warn!("sensor {} error: {}", addr, e);
+      }
+    });
+  }
+}
+
+/// Adds ` (2)`, ` (3)`, ... to a name that's taken, sensors on one host
+/// would otherwise share their ip address.
+fn claim_name(names: &Mutex<HashSet<Arc<str>>>, name: String) -> Arc<str> {
+  let mut names = names.lock().expect("sensor names lock");
+
+  let mut unique = name.clone();
+  let mut n = 2;
+  while names.contains(unique.as_str()) {
+    unique = format!("{} ({})", name, n);
+    n += 1;
+  }
+
+  let unique: Arc<str> = unique.into();
+  names.insert(unique.clone());
+  unique
+}
+
+async fn handle_sensor(
+  mut stream: TcpStream,
+  addr: SocketAddr,
+  sender: &ItemSender,
+  names: &SensorNames,
+) -> Result<()> {
+  protocol::write_handshake(&mut stream).await?;
+  protocol::read_handshake(&mut stream).await?;
+
+  let mut reader = BufReader::new(stream);
+  let Some(Message::Hello(hello)) = protocol::read_message(&mut reader).await? else {
+    bail!("sensor didn't say hello");
+  };
+
+  let name = claim_name(names, hello.name.unwrap_or_else(|| addr.ip().to_string()));
+  info!("sensor {:?} connected from {}", name, addr);
+
+  let result = if sender
+    .send(Ok(CaptureItem::SensorStatus(name.clone(), true)))
+    .await
+    .is_err()
+  {
+    Ok(())
+  } else {
+    forward_items(&mut reader, &name, hello.radiotap, sender).await
+  };
+
+  names.lock().expect("sensor names lock").remove(&name);
+  info!("sensor {:?} disconnected", name);
+  let _ = sender
+    .send(Ok(CaptureItem::SensorStatus(name, false)))
+    .await;
+
+  result
+}
+
+async fn forward_items(
+  reader: &mut BufReader<TcpStream>,
+  name: &Arc<str>,
+  is_radiotap: bool,
+  sender: &ItemSender,
+) -> Result<()> {
+  let mut id = 0;
+
+  loop {
+    let maybe_message = tokio::select! {
+      result = protocol::read_message(reader) => result?,
+      () = sender.closed() => return Ok(()),
+    };
+
+    let item = match maybe_message {
+      None => return Ok(()),
+
+      Some(Message::Packet(timestamp, bytes)) => {
+        id += 1;
+        decode_packet(id, is_radiotap, timestamp, bytes, Some(name.clone())).map(CaptureItem::Frame)
+      }
+
+      Some(Message::Stats(stats)) => Ok(CaptureItem::SensorStats(name.clone(), stats)),
+
+      Some(Message::Hello(_)) => bail!("sensor said hello twice"),
+    };
+
+    if sender.send(item).await.is_err() {
+      return Ok(());
+    }
+  }
+}
+
+#[test]
+fn test_claim_name() {
+  let names = Mutex::default();
+
+  assert_eq!(&*claim_name(&names, "10.0.0.2".to_string()), "10.0.0.2");
+  assert_eq!(&*claim_name(&names, "10.0.0.2".to_string()), "10.0.0.2 (2)");
+  assert_eq!(&*claim_name(&names, "10.0.0.2".to_string()), "10.0.0.2 (3)");
+
+  names.lock().unwrap().remove("10.0.0.2");
+  assert_eq!(&*claim_name(&names, "10.0.0.2".to_string()), "10.0.0.2");
+}
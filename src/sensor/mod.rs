@@ -0,0 +1,106 @@
+pub mod collector;
+mod protocol;
+
+use self::protocol::{Hello, Message};
+use crate::{
+  error::Result,
+  packet_capture::{get_packet_stream, CaptureType, Packet},
+};
+use futures::prelude::*;
+use log::{info, warn};
+use std::{io, time::Duration};
+use tokio::{
+  io::{AsyncWriteExt, BufWriter},
+  net::TcpStream,
+  time,
+};
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Streams undecoded packets to the collector at `collector_addr` until the
+/// capture ends, reconnecting if the connection is lost.
+///
+/// Packets captured while disconnected are dropped by pcap.
+pub async fn run(
+  capture_type: CaptureType,
+  collector_addr: String,
+  name: Option<String>,
+) -> Result<()> {
+  let (is_radiotap, packets) = get_packet_stream(capture_type)?;
+  let mut packets = packets.boxed();
+
+  let hello = Hello {
+    name,
+    radiotap: is_radiotap,
+  };
+
+  loop {
+    let stream = match connect(&collector_addr, &hello).await {
+      Ok(stream) => stream,
+
+      // handshake problems like a version mismatch won't fix themselves
+      Err(e) if e.downcast_ref::<io::Error>().is_none() => return Err(e),
+
+      Err(e) => {
+        warn!("couldn't connect to collector {}: {}", collector_addr, e);
+        time::sleep(RECONNECT_INTERVAL).await;
+        continue;
+      }
+    };
+
+    info!("connected to collector {}", collector_addr);
+
+    match forward_packets(stream, &mut packets).await {
+      Ok(()) => {
+        info!("capture finished");
+        return Ok(());
+      }
+
+      Err(e) => warn!("lost collector {}: {}", collector_addr, e),
+    }
+  }
+}
+
+async fn connect(collector_addr: &str, hello: &Hello) -> Result<TcpStream> {
+  let mut stream = TcpStream::connect(collector_addr).await?;
+  stream.set_nodelay(true)?;
+
+  protocol::write_handshake(&mut stream).await?;
+  protocol::read_handshake(&mut stream).await?;
+  protocol::write_message(&mut stream, &Message::Hello(hello.clone())).await?;
+
+  Ok(stream)
+}
+
+async fn forward_packets<S>(stream: TcpStream, packets: &mut S) -> Result<()>
+where
+  S: Stream<Item = Result<Packet>> + Unpin,
+{
+  let mut writer = BufWriter::new(stream);
+
+  loop {
+    // batch up writes while packets are coming in quickly
+    let maybe_result = if let Some(maybe_result) = packets.next().now_or_never() {
+      maybe_result
+    } else {
+      writer.flush().await?;
+      packets.next().await
+    };
+
+    let Some(result) = maybe_result else {
+      writer.shutdown().await?;
+      return Ok(());
+    };
+
+    let message = match result {
+      Ok(Packet::Data(timestamp, bytes)) => Message::Packet(timestamp, bytes),
+      Ok(Packet::Stats(stats)) => Message::Stats(stats),
+      Err(e) => {
+        warn!("capture error: {}", e);
+        continue;
+      }
+    };
+
+    protocol::write_message(&mut writer, &message).await?;
+  }
+}
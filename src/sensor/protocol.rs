@@ -0,0 +1,179 @@
+//! Sensor → collector protocol over TCP.
+//!
+//! Both sides start by sending `MAGIC` and their `VERSION` as a big endian
+//! u16, and hang up if the other side's doesn't match. After that the sensor
+//! sends a `Hello`, then any number of messages, each a tag byte followed by
+//! a big endian u32 length and the payload. Packets start with their capture
+//! time as big endian u64 microseconds since the unix epoch.
+
+use crate::{
+  error::{bail, Result},
+  packet_capture::CaptureStats,
+};
+use serde::{Deserialize, Serialize};
+use std::{io::ErrorKind, time::Duration};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub const MAGIC: [u8; 4] = *b"WVSP";
+
+/// Bump on any change to the handshake or message layout.
+pub const VERSION: u16 = 2;
+
+// way bigger than any 802.11 frame
+const MAX_PAYLOAD_LEN: u32 = 1024 * 1024;
+
+const TAG_HELLO: u8 = 0;
+const TAG_PACKET: u8 = 1;
+const TAG_STATS: u8 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hello {
+  /// defaults to the sensor's ip address on the collector
+  pub name: Option<String>,
+  /// packets start with a radiotap header
+  pub radiotap: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+  Hello(Hello),
+  /// raw packet as captured, decoded by the collector
+  Packet(Duration, Vec<u8>), // timestamp, bytes
+  Stats(CaptureStats),
+}
+
+pub async fn write_handshake<W: AsyncWrite + Unpin>(writer: &mut W) -> Result<()> {
+  writer.write_all(&MAGIC).await?;
+  writer.write_u16(VERSION).await?;
+  writer.flush().await?;
+
+  Ok(())
+}
+
+pub async fn read_handshake<R: AsyncRead + Unpin>(reader: &mut R) -> Result<()> {
+  let mut magic = [0; 4];
+  reader.read_exact(&mut magic).await?;
+  if magic != MAGIC {
+    bail!("not a wifi-visualizer sensor connection");
+  }
+
+  let version = reader.read_u16().await?;
+  if version != VERSION {
+    bail!(
+      "protocol version mismatch, ours is {} but theirs is {}",
+      VERSION,
+      version
+    );
+  }
+
+  Ok(())
+}
+
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Message) -> Result<()> {
+  let (tag, payload) = match message {
+    Message::Hello(hello) => (TAG_HELLO, serde_json::to_vec(hello)?),
+    Message::Packet(timestamp, bytes) => {
+      let micros = u64::try_from(timestamp.as_micros())?;
+      let mut payload = micros.to_be_bytes().to_vec();
+      payload.extend_from_slice(bytes);
+      (TAG_PACKET, payload)
+    }
+    Message::Stats(stats) => (TAG_STATS, serde_json::to_vec(stats)?),
+  };
+
+  let len = u32::try_from(payload.len())?;
+  if len > MAX_PAYLOAD_LEN {
+    bail!("message too big ({} bytes)", len);
+  }
+
+  writer.write_u8(tag).await?;
+  writer.write_u32(len).await?;
+  writer.write_all(&payload).await?;
+
+  Ok(())
+}
+
+/// `None` when the other side closed the connection between messages.
+pub async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Message>> {
+  let tag = match reader.read_u8().await {
+    Ok(tag) => tag,
+    Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+    Err(e) => return Err(e.into()),
+  };
+
+  let len = reader.read_u32().await?;
+  if len > MAX_PAYLOAD_LEN {
+    bail!("message too big ({} bytes)", len);
+  }
+
+  let mut payload = vec![0; len as usize];
+  reader.read_exact(&mut payload).await?;
+
+  let message = match tag {
+    TAG_HELLO => Message::Hello(serde_json::from_slice(&payload)?),
+    TAG_PACKET => {
+      if payload.len() < 8 {
+        bail!("packet without a timestamp");
+      }
+      let bytes = payload.split_off(8);
+      let mut micros = [0; 8];
+      micros.copy_from_slice(&payload);
+
+      Message::Packet(Duration::from_micros(u64::from_be_bytes(micros)), bytes)
+    }
+    TAG_STATS => Message::Stats(serde_json::from_slice(&payload)?),
+    _ => bail!("unknown message tag {}", tag),
+  };
+
+  Ok(Some(message))
+}
+
+#[test]
+fn test_round_trip() {
+  let messages = vec![
+    Message::Hello(Hello {
+      name: Some("kitchen".to_string()),
+      radiotap: true,
+    }),
+    Message::Packet(
+      Duration::from_micros(1_600_000_000_123_456),
+      vec![1, 2, 3, 4],
+    ),
+    Message::Stats(CaptureStats {
+      received: 10,
+      dropped: 1,
+      if_dropped: 0,
+    }),
+  ];
+
+  tokio::runtime::Runtime::new().unwrap().block_on(async {
+    let (mut sensor, mut collector) = tokio::io::duplex(64);
+
+    let sent = messages.clone();
+    let writer = tokio::spawn(async move {
+      write_handshake(&mut sensor).await.unwrap();
+      for message in &sent {
+        write_message(&mut sensor, message).await.unwrap();
+      }
+    });
+
+    read_handshake(&mut collector).await.unwrap();
+    let mut received = Vec::new();
+    while let Some(message) = read_message(&mut collector).await.unwrap() {
+      received.push(message);
+    }
+
+    writer.await.unwrap();
+    assert_eq!(received, messages);
+  });
+}
+
+#[test]
+fn test_version_mismatch() {
+  tokio::runtime::Runtime::new().unwrap().block_on(async {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&(VERSION + 1).to_be_bytes());
+
+    assert!(read_handshake(&mut &bytes[..]).await.is_err());
+  });
+}
@@ -0,0 +1,54 @@
+use crate::error::Result;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Signal {
+  Interrupt,
+  #[cfg(unix)]
+  Terminate,
+}
+
+impl Signal {
+  /// Exit status a shell would report for a process killed by this signal.
+  pub fn exit_code(self) -> i32 {
+    match self {
+      Self::Interrupt => 128 + 2,
+      #[cfg(unix)]
+      Self::Terminate => 128 + 15,
+    }
+  }
+}
+
+impl fmt::Display for Signal {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Interrupt => write!(f, "SIGINT"),
+      #[cfg(unix)]
+      Self::Terminate => write!(f, "SIGTERM"),
+    }
+  }
+}
+
+pub async fn wait_for_signal() -> Result<Signal> {
+  #[cfg(unix)]
+  {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+
+    tokio::select! {
+      result = tokio::signal::ctrl_c() => {
+        result?;
+        Ok(Signal::Interrupt)
+      }
+
+      _ = terminate.recv() => Ok(Signal::Terminate),
+    }
+  }
+
+  #[cfg(not(unix))]
+  {
+    tokio::signal::ctrl_c().await?;
+    Ok(Signal::Interrupt)
+  }
+}
@@ -1,66 +1,97 @@
 use crate::{
   error::{Error, Result},
-  events::{handle_frame, Event, Store},
-  packet_capture::{get_capture_stream, CaptureType},
+  events::Event,
+  pipeline::Pipeline,
 };
 use futures::prelude::*;
-use log::{error, info};
-use std::pin::Pin;
+use log::{error, info, warn};
+use tokio::sync::broadcast::error::RecvError;
 use warp::filters::ws::{Message, WebSocket};
 
-pub async fn start(ws: WebSocket, capture_type: CaptureType) -> Result<()> {
-  let (mut ws_sender, _ws_receiver) = ws.split();
+// https://www.rfc-editor.org/rfc/rfc6455#section-7.4.1
+const CLOSE_GOING_AWAY: u16 = 1001;
 
-  let mut events_stream: Pin<Box<dyn Stream<Item = Result<Vec<Event>>>>> =
-    match start_capture_event_stream(capture_type).await {
-      Ok(events_stream) => events_stream.boxed(),
-      Err(e) => stream::iter(vec![Err(e)]).boxed(),
-    };
+pub async fn start(ws: WebSocket, pipeline: Pipeline) -> Result<()> {
+  let (mut ws_sender, mut ws_receiver) = ws.split();
 
-  while let Some(result) = events_stream.next().await {
-    let was_error = result.is_err();
+  let (snapshot, mut events_receiver) = pipeline.subscribe();
+  let mut shutdown_receiver = pipeline.shutdown_receiver();
+  if !send_events(&mut ws_sender, &snapshot).await? {
+    return Ok(());
+  }
 
-    let message = result
-      .or_else(|err| Ok(vec![Event::Error(format!("{}", err))]))
-      .and_then(|events| serde_json::to_string(&events).map_err(Error::from))
-      .map(Message::text)?;
+  loop {
+    tokio::select! {
+      result = events_receiver.recv() => {
+        let sent = match result {
+          Ok(events) => send_events(&mut ws_sender, &events).await?,
 
-    if let Err(err) = ws_sender.send(message).await {
-      error!("websocket sink error: {}", err);
-      return Ok(());
-    }
+          Err(RecvError::Lagged(skipped)) => {
+            warn!("websocket fell behind by {} messages, resending state", skipped);
+
+            let (snapshot, new_events_receiver) = pipeline.subscribe();
+            events_receiver = new_events_receiver;
+            send_events(&mut ws_sender, &snapshot).await?
+          }
+
+          Err(RecvError::Closed) => break,
+        };
+
+        if !sent {
+          return Ok(());
+        }
+      }
+
+      maybe_message = ws_receiver.next() => {
+        match maybe_message {
+          Some(Ok(_message)) => {}
+
+          Some(Err(err)) => {
+            error!("websocket stream error: {}", err);
+            return Ok(());
+          }
 
-    if was_error {
-      break;
+          None => {
+            info!("websocket closed by client");
+            return Ok(());
+          }
+        }
+      }
+
+      _ = shutdown_receiver.changed() => break,
     }
   }
 
+  ws_sender
+    .send(Message::close_with(
+      CLOSE_GOING_AWAY,
+      "server shutting down",
+    ))
+    .await?;
   ws_sender.close().await?;
   info!("websocket closed");
 
   Ok(())
 }
 
-async fn start_capture_event_stream(
-  capture_type: CaptureType,
-) -> Result<impl Stream<Item = Result<Vec<Event>>>> {
-  let capture_stream = get_capture_stream(capture_type).await?;
-  let mut store = Store::new();
-
-  Ok(
-    capture_stream
-      .map(move |result| {
-        let frame_with_radiotap = result?;
-        Ok(handle_frame(&mut store, &frame_with_radiotap)?)
-      })
-      .map(|result| {
-        if let Err(e) = result {
-          error!("packet parse error: {:?}", e);
-          Ok(vec![])
-        } else {
-          result
-        }
-      })
-      .try_filter(|events| future::ready(!events.is_empty())),
-  )
+/// Returns false if the client went away.
+async fn send_events<S>(ws_sender: &mut S, events: &[Event]) -> Result<bool>
+where
+  S: Sink<Message> + Unpin,
+  S::Error: std::fmt::Display,
+{
+  if events.is_empty() {
+    return Ok(true);
+  }
+
+  let message = serde_json::to_string(events)
+    .map_err(Error::from)
+    .map(Message::text)?;
+
+  if let Err(err) = ws_sender.send(message).await {
+    error!("websocket sink error: {}", err);
+    return Ok(false);
+  }
+
+  Ok(true)
 }
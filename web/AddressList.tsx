@@ -26,7 +26,7 @@ function AccessPointsTable({
   addresses,
   onAddressHover,
 }: {
-  addresses: AddressOptions;
+  addresses: { [id: string]: AddressOptions };
   onAddressHover: (id: string, hovered: boolean) => void;
 }) {
   return (
@@ -65,7 +65,7 @@ function AccessPointsTable({
                 {beaconQuality ? `${Math.floor(beaconQuality * 100)}%` : ""}
               </td>
               <td>{rate}</td>
-              <td>{id}</td>
+              <td>{address.label || id}</td>
               <td>{channel}</td>
               <td>{ssid}</td>
             </tr>
@@ -80,7 +80,7 @@ function StationsTable({
   addresses,
   onAddressHover,
 }: {
-  addresses: AddressOptions;
+  addresses: { [id: string]: AddressOptions };
   onAddressHover: (id: string, hovered: boolean) => void;
 }) {
   return (
@@ -89,6 +89,7 @@ function StationsTable({
         <tr>
           <th>Signal</th>
           <th>Rate</th>
+          <th>PHY</th>
           <th>Bssid</th>
           <th>Station</th>
         </tr>
@@ -99,7 +100,7 @@ function StationsTable({
             ([_, address]) => !address.accessPointInfo
           )
         ).map(([id, address]: [string, AddressOptions]) => {
-          const { signal, rate } = address;
+          const { signal, rate, phy } = address;
 
           const bssids = address.connections
             ? Object.entries(address.connections)
@@ -130,8 +131,18 @@ function StationsTable({
             >
               <td>{signal}</td>
               <td>{rate}</td>
-              <td>{bssid}</td>
-              <td>{id}</td>
+              <td>
+                {phy && phy.rate
+                  ? `${phy.rate} Mbps ${phy.kind || ""} ${
+                      phy.spatial_streams ? `${phy.spatial_streams}ss` : ""
+                    }`
+                  : ""}
+              </td>
+              <td>{(addresses[bssid] && addresses[bssid].label) || bssid}</td>
+              <td>
+                {address.label || id}
+                {address.randomized ? " (random)" : ""}
+              </td>
             </tr>
           );
         })}
@@ -3,11 +3,26 @@ import React from "react";
 import vis from "vis-network";
 import NetworkElement from "./Network";
 import { companyToIconCode, hashMacs } from "./helpers";
-import { ConnectionType, MacAddress } from "./interfaceTypes";
+import {
+  AntennaSignal,
+  ConnectionType,
+  DeviceFingerprint,
+  MacAddress,
+  PhyInfo,
+  Position,
+  Roam,
+  Security,
+  SensorSignal,
+  SignalStats,
+  Throughput,
+} from "./interfaceTypes";
 import { oui } from "./oui";
 
 const known = ["98-d6-f7-01-01-00", "48-a4-72-1b-d3-43"];
 
+// beacon flood access points are collapsed into this node
+const FAKE_ACCESS_POINTS = "fake-access-points";
+
 const connectionTypeToColor: { [kind: string]: string } = {
   Associated: "blue",
   Authentication: "green",
@@ -21,6 +36,7 @@ export interface AddressOptions {
   accessPointInfo?: {
     ssid: string;
     channel?: number;
+    security?: Security;
   };
   beaconQuality?: number;
 
@@ -29,9 +45,42 @@ export interface AddressOptions {
   // loss?: number;
 
   signal?: number | false;
+  antennaSignals?: AntennaSignal[];
+  signalStats?: SignalStats[];
+  sensorSignals?: SensorSignal[];
+  position?: Position;
+  // meters
+  distance?: number;
   rate?: number | false;
+  // % of frames that were retries
+  retryRate?: number;
+  // to receiver
+  throughput?: { [to: string]: Throughput };
+  // % of the time spent transmitting
+  airtime?: number;
+  phy?: PhyInfo;
+  badFrames?: number;
+  // by subtype name
+  frameStats?: { [subtype: string]: number };
 
   hovered?: boolean;
+  // from the server's oui database, which can be newer than ours
+  vendor?: string;
+  fingerprint?: DeviceFingerprint;
+  // locally administered, probably random
+  randomized?: boolean;
+  // other random addresses of the same device
+  sameDevice?: MacAddress[];
+  // times the station moved to another access point
+  roams?: number;
+  lastRoam?: Roam;
+
+  // from the watchlist, shown instead of the address
+  label?: string;
+  // false after a watched address goes quiet
+  present?: boolean;
+  // from a beacon flood
+  fake?: boolean;
 }
 
 interface AddressNetworkProps {
@@ -62,19 +111,44 @@ export default class AddressNetwork extends React.PureComponent<
   ) {
     // console.log(`AddressManager updateNetwork ${id}`);
 
-    const { accessPointInfo, signal, rate, beaconQuality, hovered } = address;
+    const { accessPointInfo, signal, rate, beaconQuality, hovered, fake } =
+      address;
+
+    const nodeId = fake ? FAKE_ACCESS_POINTS : id;
 
     const color =
-      known.indexOf(id) !== -1
+      address.present === false
+        ? "grey"
+        : known.indexOf(id) !== -1 || address.label
         ? "#ff00ff"
         : accessPointInfo
         ? "green"
         : "#2B7CE9";
 
-    const company = oui(id);
+    const company = address.vendor || oui(id);
 
-    let label = "";
+    let label = address.label ? `${address.label}\n` : "";
     let title = company ? `${id} (${company})` : id;
+    if (address.randomized) {
+      title += " (random)";
+    }
+    if (address.fingerprint) {
+      const { hash, os, device } = address.fingerprint;
+      const guess = [os, device].filter((part) => part).join(" ");
+      title += `<br />${guess || "unknown device"} (${hash.toString(16)})`;
+    }
+    if (address.roams) {
+      title += `<br />roamed ${address.roams} times`;
+      if (address.lastRoam) {
+        const { from_bssid, to_bssid, duration_on_prev } = address.lastRoam;
+        title += `, last from ${from_bssid} to ${to_bssid} after ${Math.round(
+          duration_on_prev
+        )}s`;
+      }
+    }
+    if (address.label) {
+      title = `${address.label}<br />${title}`;
+    }
 
     if (accessPointInfo) {
       const { ssid, channel } = accessPointInfo;
@@ -99,16 +173,20 @@ export default class AddressNetwork extends React.PureComponent<
 
     const code = companyToIconCode(company);
 
-    nodes[id] = {
-      id,
-      title,
-      label,
-      icon: {
-        code,
-        size,
-        color,
-      },
-    };
+    if (fake) {
+      delete nodes[id];
+    } else {
+      nodes[id] = {
+        id,
+        title,
+        label,
+        icon: {
+          code,
+          size,
+          color,
+        },
+      };
+    }
 
     if (
       address.connections &&
@@ -120,14 +198,14 @@ export default class AddressNetwork extends React.PureComponent<
           return;
         }
 
-        const edgeId = hashMacs(id, otherId);
+        const edgeId = hashMacs(nodeId, otherId);
         const color = connectionTypeToColor[kind];
         const dashes = kind === "Disassociated" || kind === "InRange";
         const width = kind === "InRange" ? 0.1 : 3;
 
         edges[edgeId] = {
           id: edgeId,
-          from: id,
+          from: nodeId,
           to: otherId,
           color: { color: color, highlight: color, hover: color },
           dashes,
@@ -135,11 +213,27 @@ export default class AddressNetwork extends React.PureComponent<
         };
       });
     }
+
+    if (address.sameDevice && address.sameDevice !== lastAddress.sameDevice) {
+      address.sameDevice.forEach((otherId) => {
+        const edgeId = `same-device-${hashMacs(id, otherId)}`;
+
+        edges[edgeId] = {
+          id: edgeId,
+          from: nodeId,
+          to: otherId,
+          color: { color: "purple", highlight: "purple", hover: "purple" },
+          dashes: [2, 8],
+          width: 2,
+        };
+      });
+    }
   }
 
   updateAddresses(
     lastAddresses: { [id: string]: AddressOptions },
-    addresses: { [id: string]: AddressOptions }
+    addresses: { [id: string]: AddressOptions },
+    allAddresses: { [id: string]: AddressOptions }
   ) {
     const { nodes: a, edges: b } = this.state;
     const nodes = { ...a };
@@ -150,11 +244,27 @@ export default class AddressNetwork extends React.PureComponent<
       this.updateNetwork(id, lastAddress, address, nodes, edges);
     });
 
+    const fakeCount = Object.values(allAddresses).filter(
+      (address) => address.fake
+    ).length;
+    if (fakeCount > 0) {
+      nodes[FAKE_ACCESS_POINTS] = {
+        id: FAKE_ACCESS_POINTS,
+        title: "access points from a beacon flood",
+        label: `${fakeCount} fake access points`,
+        icon: {
+          code: companyToIconCode(undefined),
+          size: 50,
+          color: "red",
+        },
+      };
+    }
+
     this.setState({ nodes, edges });
   }
 
   componentDidMount() {
-    this.updateAddresses({}, this.props.addresses);
+    this.updateAddresses({}, this.props.addresses, this.props.addresses);
   }
 
   componentWillReceiveProps(nextProps: AddressNetworkProps) {
@@ -165,7 +275,7 @@ export default class AddressNetwork extends React.PureComponent<
           o[id] = address;
         }
       });
-      this.updateAddresses(this.props.addresses, o);
+      this.updateAddresses(this.props.addresses, o, nextProps.addresses);
     }
   }
 
@@ -3,7 +3,13 @@ import AddressNetwork, { AddressOptions } from "./AddressNetwork";
 import AddressList from "./AddressList";
 import { IToaster, Alert, Intent } from "@blueprintjs/core";
 import { byteArrayToString, setNamedTimeout } from "./helpers";
-import { FrameEvent, MacAddress } from "./interfaceTypes";
+import {
+  Alert as AlertData,
+  CaptureStats,
+  ChannelUtilization,
+  FrameEvent,
+  MacAddress,
+} from "./interfaceTypes";
 
 interface AddressViewProps {
   toaster: IToaster;
@@ -14,6 +20,13 @@ interface AddressViewState {
   addresses: { [id: string]: AddressOptions };
   error?: string;
   hovered?: string;
+  captureStats?: CaptureStats;
+  // by frequency
+  channelUtilization: { [frequency: number]: ChannelUtilization };
+  sensors: { [name: string]: { connected: boolean; stats?: CaptureStats } };
+  totalBadFrames?: [number, number];
+  totalFrameStats?: { [subtype: string]: number };
+  alerts: AlertData[];
 }
 
 export default class AddressView extends React.Component<
@@ -23,6 +36,9 @@ export default class AddressView extends React.Component<
   state: AddressViewState = {
     connected: false,
     addresses: {},
+    sensors: {},
+    channelUtilization: {},
+    alerts: [],
     error: undefined,
   };
 
@@ -30,16 +46,16 @@ export default class AddressView extends React.Component<
     // console.log(`handleFrameEvent`, event);
 
     if (event.type === "NewAddress") {
-      const id = event.data;
+      const [id, vendor] = event.data;
 
-      this.updateAddress(id, {});
+      this.updateAddress(id, vendor ? { vendor } : {});
     } else if (event.type === "AccessPoint") {
       const [id, info] = event.data;
-      const { ssid: ssidBytes, channel } = info;
+      const { ssid: ssidBytes, channel, security } = info;
       const ssid = byteArrayToString(ssidBytes);
 
       this.updateAddress(id, {
-        accessPointInfo: { ssid, channel },
+        accessPointInfo: { ssid, channel, security },
       });
     } else if (event.type === "Connection") {
       const [from, to, kind] = event.data;
@@ -67,13 +83,42 @@ export default class AddressView extends React.Component<
     } else if (event.type === "ProbeRequest") {
       const [id, ssidBytes] = event.data;
       const ssid = byteArrayToString(ssidBytes);
+      const probeRequests = this.state.addresses[id].probeRequests || [];
 
-      this.updateAddress(id, {
-        probeRequests: [
-          ...(this.state.addresses[id].probeRequests || []),
-          ssid,
-        ],
+      // state is sent again if we fall behind
+      if (!probeRequests.includes(ssid)) {
+        this.updateAddress(id, {
+          probeRequests: [...probeRequests, ssid],
+        });
+      }
+    } else if (event.type === "DeviceFingerprint") {
+      const [id, fingerprint] = event.data;
+
+      this.updateAddress(id, { fingerprint });
+    } else if (event.type === "Roam") {
+      const roam = event.data;
+
+      this.updateAddress(roam.sta, { lastRoam: roam });
+    } else if (event.type === "RoamCount") {
+      const [id, roams] = event.data;
+
+      this.updateAddress(id, { roams });
+    } else if (event.type === "Randomized") {
+      const id = event.data;
+
+      this.updateAddress(id, { randomized: true });
+    } else if (event.type === "SameDevice") {
+      const ids = event.data;
+
+      ids.forEach((id) => {
+        this.updateAddress(id, {
+          sameDevice: ids.filter((other) => other !== id),
+        });
       });
+    } else if (event.type === "Presence") {
+      const [id, label, present] = event.data;
+
+      this.updateAddress(id, { label, present });
       // } else if (event.type === "Loss") {
       //   const [id, numLost, numReceived] = event.data;
 
@@ -83,10 +128,35 @@ export default class AddressView extends React.Component<
       //     loss,
       //   });
     } else if (event.type === "Signal") {
-      const [id, signal] = event.data;
+      const [id, signal, antennaSignals] = event.data;
 
       this.updateAddress(id, {
         signal,
+        antennaSignals,
+      });
+    } else if (event.type === "SignalStats") {
+      const [id, signalStats] = event.data;
+
+      this.updateAddress(id, {
+        signalStats,
+      });
+    } else if (event.type === "SensorSignals") {
+      const [id, sensorSignals] = event.data;
+
+      this.updateAddress(id, {
+        sensorSignals,
+      });
+    } else if (event.type === "Position") {
+      const [id, position] = event.data;
+
+      this.updateAddress(id, {
+        position,
+      });
+    } else if (event.type === "Distance") {
+      const [id, distance] = event.data;
+
+      this.updateAddress(id, {
+        distance,
       });
     } else if (event.type === "Rate") {
       const [id, rate] = event.data;
@@ -94,11 +164,142 @@ export default class AddressView extends React.Component<
       this.updateAddress(id, {
         rate,
       });
+    } else if (event.type === "RetryRate") {
+      const [id, retryRate] = event.data;
+
+      this.updateAddress(id, {
+        retryRate,
+      });
+    } else if (event.type === "Throughput") {
+      const [from, to, throughput] = event.data;
+
+      // several links from one transmitter come in the same batch
+      this.setState((lastState) => ({
+        addresses: {
+          ...lastState.addresses,
+          [from]: {
+            ...lastState.addresses[from],
+            throughput: {
+              ...lastState.addresses[from].throughput,
+              [to]: throughput,
+            },
+          },
+        },
+      }));
+    } else if (event.type === "Airtime") {
+      const [id, airtime] = event.data;
+
+      this.updateAddress(id, {
+        airtime,
+      });
+    } else if (event.type === "ChannelUtilization") {
+      const [frequency, utilization] = event.data;
+
+      this.setState((lastState) => ({
+        channelUtilization: {
+          ...lastState.channelUtilization,
+          [frequency]: utilization,
+        },
+      }));
     } else if (event.type === "BeaconQuality") {
       const [id, received, correct] = event.data;
       this.updateAddress(id, {
         beaconQuality: received / correct,
       });
+    } else if (event.type === "Phy") {
+      const [id, phy] = event.data;
+      this.updateAddress(id, {
+        phy,
+      });
+    } else if (event.type === "BadFrames") {
+      const [id, badFrames] = event.data;
+      this.updateAddress(id, {
+        badFrames,
+      });
+    } else if (event.type === "TotalBadFrames") {
+      this.setState({ totalBadFrames: event.data });
+    } else if (event.type === "FrameStats") {
+      const [id, frameStats] = event.data;
+
+      this.updateAddress(id, {
+        frameStats,
+      });
+    } else if (event.type === "TotalFrameStats") {
+      this.setState({ totalFrameStats: event.data });
+    } else if (event.type === "CaptureStats") {
+      const captureStats = event.data;
+      const { captureStats: lastCaptureStats } = this.state;
+
+      if (
+        lastCaptureStats &&
+        (captureStats.dropped > lastCaptureStats.dropped ||
+          captureStats.if_dropped > lastCaptureStats.if_dropped)
+      ) {
+        this.props.toaster.show({
+          message: `capture dropped ${
+            captureStats.dropped - lastCaptureStats.dropped
+          } packets in kernel, ${
+            captureStats.if_dropped - lastCaptureStats.if_dropped
+          } in interface`,
+          intent: "warning",
+        });
+      }
+
+      this.setState({ captureStats });
+    } else if (event.type === "Sensor") {
+      const [name, connected] = event.data;
+      const lastSensor = this.state.sensors[name];
+
+      // snapshots repeat the state, only toast changes
+      if (!lastSensor || lastSensor.connected !== connected) {
+        this.props.toaster.show({
+          message: `sensor ${name} ${connected ? "connected" : "disconnected"}`,
+          intent: connected ? "success" : "warning",
+        });
+      }
+
+      this.setState((lastState) => ({
+        sensors: {
+          ...lastState.sensors,
+          [name]: { ...lastState.sensors[name], connected },
+        },
+      }));
+    } else if (event.type === "SensorStats") {
+      const [name, stats] = event.data;
+
+      this.setState((lastState) => ({
+        sensors: {
+          ...lastState.sensors,
+          [name]: { connected: true, ...lastState.sensors[name], stats },
+        },
+      }));
+    } else if (event.type === "Alert") {
+      const alert = event.data;
+
+      this.props.toaster.show({
+        message: alert.message,
+        intent:
+          alert.severity === "critical"
+            ? "danger"
+            : alert.severity === "warning"
+            ? "warning"
+            : "primary",
+        icon: "warning-sign",
+      });
+
+      this.setState((lastState) => ({
+        alerts: [...lastState.alerts, alert],
+      }));
+    } else if (event.type === "FakeAccessPoints") {
+      const ids = event.data;
+
+      ids.forEach((id) => {
+        this.updateAddress(id, { fake: true });
+      });
+    } else if (event.type === "RealAccessPoint") {
+      const id = event.data;
+
+      this.updateAddress(id, { fake: false });
     } else if (event.type === "Error") {
       const error = event.data;
       console.warn("Error", error);
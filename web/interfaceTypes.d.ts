@@ -1,9 +1,12 @@
 export type MacAddress = string;
 export type ByteArray = number[];
 
+export type Security = "open" | "owe" | "wep" | "wpa" | "wpa2" | "wpa3";
+
 export interface AccessPointInfo {
   ssid: ByteArray;
   channel?: number;
+  security?: Security;
 }
 
 export type ConnectionType =
@@ -18,7 +21,8 @@ export interface FrameEventPrototype {
 }
 export interface NewAddressFrameEvent extends FrameEventPrototype {
   type: "NewAddress";
-  data: MacAddress;
+  // vendor
+  data: [MacAddress, string | null];
 }
 export interface AccessPointFrameEvent extends FrameEventPrototype {
   type: "AccessPoint";
@@ -32,6 +36,47 @@ export interface ProbeRequestFrameEvent extends FrameEventPrototype {
   type: "ProbeRequest";
   data: [MacAddress, ByteArray];
 }
+export interface DeviceFingerprint {
+  // shown as hex in the signature database
+  hash: number;
+  os?: string;
+  // phone, laptop, ...
+  device?: string;
+}
+export interface DeviceFingerprintFrameEvent extends FrameEventPrototype {
+  type: "DeviceFingerprint";
+  data: [MacAddress, DeviceFingerprint];
+}
+export interface Roam {
+  sta: MacAddress;
+  from_bssid: MacAddress;
+  to_bssid: MacAddress;
+  // of to_bssid, empty if unknown
+  ssid: number[];
+  // seconds
+  duration_on_prev: number;
+}
+export interface RoamFrameEvent extends FrameEventPrototype {
+  type: "Roam";
+  data: Roam;
+}
+export interface RoamCountFrameEvent extends FrameEventPrototype {
+  type: "RoamCount";
+  data: [MacAddress, number];
+}
+export interface RandomizedFrameEvent extends FrameEventPrototype {
+  type: "Randomized";
+  data: MacAddress;
+}
+export interface SameDeviceFrameEvent extends FrameEventPrototype {
+  type: "SameDevice";
+  data: MacAddress[];
+}
+export interface PresenceFrameEvent extends FrameEventPrototype {
+  type: "Presence";
+  // label, present
+  data: [MacAddress, string, boolean];
+}
 // export interface InactiveAddressFrameEvent extends FrameEventPrototype {
 //   type: "InactiveAddress";
 //   data: MacAddress[];
@@ -43,10 +88,102 @@ export interface ProbeRequestFrameEvent extends FrameEventPrototype {
 //    */
 //   data: [MacAddress, number, number];
 // }
+export interface AntennaSignal {
+  antenna: number;
+  signal: number;
+}
 export interface SignalEvent extends FrameEventPrototype {
   type: "Signal";
+  // combined, per antenna
+  data: [MacAddress, number, AntennaSignal[]];
+}
+export interface SignalStats {
+  window_secs: number;
+  count: number;
+  min: number;
+  max: number;
+  mean: number;
+  stddev: number;
+}
+export interface SignalStatsEvent extends FrameEventPrototype {
+  type: "SignalStats";
+  data: [MacAddress, SignalStats[]];
+}
+export interface SensorSignal {
+  sensor: string;
+  signal: number;
+}
+export interface SensorSignalsEvent extends FrameEventPrototype {
+  type: "SensorSignals";
+  data: [MacAddress, SensorSignal[]];
+}
+export interface Position {
+  // meters, same frame as the sensor positions in the config
+  x: number;
+  y: number;
+  sensors: number;
+  method: "weighted-centroid" | "trilateration";
+}
+export interface PositionEvent extends FrameEventPrototype {
+  type: "Position";
+  data: [MacAddress, Position];
+}
+export interface DistanceEvent extends FrameEventPrototype {
+  type: "Distance";
+  // meters
+  data: [MacAddress, number];
+}
+// GET /api/signal/:mac?window=seconds
+export interface SignalHistoryReport {
+  stats: SignalStats[];
+  // mean signal of every second, oldest first
+  sparkline: Array<number | null>;
+}
+export interface ChannelUtilization {
+  // estimated % of the last interval the channel was busy
+  busy: number;
+  airtime_us: number;
+  frames: number;
+}
+export interface ChannelUtilizationEvent extends FrameEventPrototype {
+  type: "ChannelUtilization";
+  // MHz
+  data: [number, ChannelUtilization];
+}
+export interface AirtimeEvent extends FrameEventPrototype {
+  type: "Airtime";
+  // % of the last interval spent transmitting
   data: [MacAddress, number];
 }
+export interface Traffic {
+  frames: number;
+  bytes: number;
+  // frames with the Retry bit set
+  retries: number;
+}
+export interface LinkTraffic {
+  management: Traffic;
+  control: Traffic;
+  data: Traffic;
+}
+export interface Throughput {
+  // since the last event, about a second
+  recent: LinkTraffic;
+  total: LinkTraffic;
+  // % of frames that were retries
+  recent_retry_rate: number;
+  total_retry_rate: number;
+}
+export interface RetryRateEvent extends FrameEventPrototype {
+  type: "RetryRate";
+  // % of the frames in Rate that were retries
+  data: [MacAddress, number];
+}
+export interface ThroughputEvent extends FrameEventPrototype {
+  type: "Throughput";
+  // transmitter, receiver
+  data: [MacAddress, MacAddress, Throughput];
+}
 export interface RateEvent extends FrameEventPrototype {
   type: "Rate";
   data: [MacAddress, number];
@@ -56,6 +193,125 @@ export interface BeaconQualityEvent extends FrameEventPrototype {
   // #received, #correct
   data: [MacAddress, number, number];
 }
+export interface PhyInfo {
+  kind?: "Legacy" | "Ht" | "Vht";
+  rate?: number;
+  mcs?: number;
+  spatial_streams?: number;
+  band?: "2.4GHz" | "5GHz" | "6GHz";
+  frequency?: number;
+  antenna?: number;
+  snr?: number;
+}
+export interface PhyEvent extends FrameEventPrototype {
+  type: "Phy";
+  data: [MacAddress, PhyInfo];
+}
+export interface BadFramesEvent extends FrameEventPrototype {
+  type: "BadFrames";
+  data: [MacAddress, number];
+}
+export interface FrameStatsEvent extends FrameEventPrototype {
+  type: "FrameStats";
+  // frames per subtype name
+  data: [MacAddress, { [subtype: string]: number }];
+}
+export interface TotalFrameStatsEvent extends FrameEventPrototype {
+  type: "TotalFrameStats";
+  data: { [subtype: string]: number };
+}
+export interface TotalBadFramesEvent extends FrameEventPrototype {
+  type: "TotalBadFrames";
+  // #bad, #total
+  data: [number, number];
+}
+export interface CaptureStats {
+  received: number;
+  dropped: number;
+  if_dropped: number;
+}
+export interface CaptureStatsEvent extends FrameEventPrototype {
+  type: "CaptureStats";
+  data: CaptureStats;
+}
+export interface SensorEvent extends FrameEventPrototype {
+  type: "Sensor";
+  // name, connected
+  data: [string, boolean];
+}
+export interface SensorStatsEvent extends FrameEventPrototype {
+  type: "SensorStats";
+  data: [string, CaptureStats];
+}
+export type RogueApReason =
+  | "UnknownBssid"
+  | "UnexpectedChannel"
+  | "SecurityMismatch"
+  | "SecurityDowngrade"
+  | "VendorMismatch";
+
+export type AlertKind =
+  | {
+      kind: "DeauthFlood";
+      details: {
+        attacker: MacAddress;
+        victims: MacAddress[];
+        broadcast: boolean;
+        // frames/s
+        rate: number;
+      };
+    }
+  | {
+      kind: "RogueAccessPoint";
+      details: {
+        bssid: MacAddress;
+        ssid: string;
+        reason: RogueApReason;
+      };
+    }
+  | {
+      kind: "BeaconFlood";
+      details: {
+        access_points: number;
+        // new access points/s
+        rate: number;
+        random_ssids: number;
+        shared_sequence_numbers: boolean;
+        same_signal: boolean;
+      };
+    }
+  | {
+      kind: "Karma";
+      details: {
+        bssid: MacAddress;
+        ssids: string[];
+      };
+    };
+export type Severity = "info" | "warning" | "critical";
+
+export type Alert = AlertKind & {
+  severity: Severity;
+  macs: MacAddress[];
+  // of each of macs
+  vendors: (string | null)[];
+  message: string;
+  // ms since the unix epoch
+  timestamp: number;
+};
+export interface AlertEvent extends FrameEventPrototype {
+  type: "Alert";
+  data: Alert;
+}
+
+export interface FakeAccessPointsEvent extends FrameEventPrototype {
+  type: "FakeAccessPoints";
+  data: MacAddress[];
+}
+export interface RealAccessPointEvent extends FrameEventPrototype {
+  type: "RealAccessPoint";
+  data: MacAddress;
+}
+
 export interface ErrorFrameEvent extends FrameEventPrototype {
   type: "Error";
   data: string;
@@ -66,9 +322,34 @@ export type FrameEvent =
   | AccessPointFrameEvent
   | ConnectionFrameEvent
   | ProbeRequestFrameEvent
+  | DeviceFingerprintFrameEvent
+  | RoamFrameEvent
+  | RoamCountFrameEvent
+  | RandomizedFrameEvent
+  | SameDeviceFrameEvent
+  | PresenceFrameEvent
   // | InactiveAddressFrameEvent
   // | LossFrameEvent
   | SignalEvent
+  | SignalStatsEvent
+  | DistanceEvent
+  | SensorSignalsEvent
+  | PositionEvent
   | RateEvent
+  | RetryRateEvent
+  | ThroughputEvent
+  | ChannelUtilizationEvent
+  | AirtimeEvent
+  | PhyEvent
   | BeaconQualityEvent
+  | BadFramesEvent
+  | TotalBadFramesEvent
+  | FrameStatsEvent
+  | TotalFrameStatsEvent
+  | CaptureStatsEvent
+  | SensorEvent
+  | SensorStatsEvent
+  | AlertEvent
+  | FakeAccessPointsEvent
+  | RealAccessPointEvent
   | ErrorFrameEvent;